@@ -5,7 +5,7 @@ use core::fmt::Debug;
 use log::{trace, error};
 
 use embedded_hal::blocking::delay::{DelayMs, DelayUs};
-use embedded_hal::blocking::spi::Transactional;
+use embedded_hal::blocking::spi::{Transactional, Operation};
 
 use driver_pal::{Reset, Busy, Ready, PinState, PrefixRead, PrefixWrite};
 use driver_pal::{Error as SpiError};
@@ -23,6 +23,14 @@ pub trait Hal<
     /// Reset the device
     fn reset(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>>;
 
+    /// Reset the device, asserting NRESET for `assert_ms` then waiting `settle_ms` for the device to start
+    ///
+    /// The default implementation calls `reset`, ignoring the provided timings.
+    fn reset_timed(&mut self, assert_ms: u32, settle_ms: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let _ = (assert_ms, settle_ms);
+        self.reset()
+    }
+
     /// Fetch radio device busy pin value
     fn get_busy(&mut self) -> Result<PinState, Error<CommsError, PinError, DelayError>>;
 
@@ -40,6 +48,17 @@ pub trait Hal<
     /// Read the specified command and data
     fn read_cmd(&mut self, command: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>>;
     
+    /// Write a sequence of commands, awaiting busy only between commands
+    ///
+    /// The default implementation issues each command with `write_cmd`.
+    fn write_cmds(&mut self, cmds: &[(u8, &[u8])]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        for (command, data) in cmds {
+            self.write_cmd(*command, data)?;
+        }
+
+        Ok(())
+    }
+
     /// Write to the specified register
     fn write_regs(&mut self, reg: u16, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>>;
     /// Read from the specified register
@@ -50,6 +69,47 @@ pub trait Hal<
     /// Read from the specified buffer
     fn read_buff(&mut self, offset: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>>;
 
+    /// Write to the specified buffer in chunks of at most `max_transfer` bytes
+    fn write_buff_chunked(&mut self, offset: u8, data: &[u8], max_transfer: usize) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let mut offset = offset;
+
+        for chunk in data.chunks(max_transfer.max(1)) {
+            self.write_buff(offset, chunk)?;
+            offset = offset.wrapping_add(chunk.len() as u8);
+        }
+
+        Ok(())
+    }
+
+    /// Read from the specified buffer in chunks of at most `max_transfer` bytes
+    fn read_buff_chunked(&mut self, offset: u8, data: &mut [u8], max_transfer: usize) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let mut offset = offset;
+
+        for chunk in data.chunks_mut(max_transfer.max(1)) {
+            self.read_buff(offset, chunk)?;
+            offset = offset.wrapping_add(chunk.len() as u8);
+        }
+
+        Ok(())
+    }
+
+    /// Put the device to sleep with the provided sleep configuration
+    /// (BUSY remains asserted while asleep so this does not await completion)
+    ///
+    /// The default implementation returns `Error::Unsupported`, as waking the device
+    /// requires an NSS transition without first awaiting BUSY (see `wakeup`).
+    fn sleep(&mut self, config: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let _ = config;
+        Err(Error::Unsupported)
+    }
+
+    /// Wake the device from sleep and wait for it to become ready
+    ///
+    /// The default implementation returns `Error::Unsupported`.
+    fn wakeup(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        Err(Error::Unsupported)
+    }
+
     /// Wait on radio device busy
     fn wait_busy(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
         // TODO: timeouts here
@@ -100,13 +160,18 @@ where
     PinError: Debug + Sync + Send,
     DelayError: Debug + Sync + Send,
 {    
-    /// Reset the radio
+    /// Reset the radio with the default timings
     fn reset(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.reset_timed(50, 20)
+    }
+
+    /// Reset the radio
+    fn reset_timed(&mut self, assert_ms: u32, settle_ms: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
         self.try_delay_ms(20).map_err(Error::Delay)?;
         self.set_reset(PinState::Low).map_err(|e| Error::from(e) )?;
-        self.try_delay_ms(50).map_err(Error::Delay)?;
+        self.try_delay_ms(assert_ms).map_err(Error::Delay)?;
         self.set_reset(PinState::High).map_err(|e| Error::from(e) )?;
-        self.try_delay_ms(20).map_err(Error::Delay)?;
+        self.try_delay_ms(settle_ms).map_err(Error::Delay)?;
 
         Ok(())
     }
@@ -145,6 +210,26 @@ where
         r
     }
 
+    /// Write a sequence of commands
+    ///
+    /// The device latches each command on the rising edge of NSS, so commands cannot
+    /// share a single chip-select frame. Instead these are issued back-to-back with one
+    /// busy wait between each, rather than the two incurred by sequential `write_cmd` calls.
+    fn write_cmds(&mut self, cmds: &[(u8, &[u8])]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.wait_busy()?;
+
+        for (command, data) in cmds {
+            let out_buf: [u8; 1] = [*command];
+
+            trace!("write_cmds cmd: {:02x?} data: {:02x?}", out_buf, data);
+
+            self.try_prefix_write(&out_buf, data).map_err(|e| Error::from(e) )?;
+            self.wait_busy()?;
+        }
+
+        Ok(())
+    }
+
     /// Read the specified command and data
     fn read_cmd<'a>(&mut self, command: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
         // Setup register read command
@@ -228,5 +313,263 @@ where
 
         r
     }
+
+    /// Put the device to sleep
+    fn sleep(&mut self, config: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let out_buf: [u8; 1] = [ Commands::SetSleep as u8 ];
+
+        trace!("sleep cmd: {:02x?} config: {:02x?}", out_buf, config);
+
+        self.wait_busy()?;
+        self.try_prefix_write(&out_buf, &[config]).map_err(|e| e.into() )
+    }
+
+    /// Wake the device from sleep
+    fn wakeup(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        // Any NSS falling edge wakes the device, so issue a status read
+        // without first waiting on BUSY (which is held high while asleep)
+        let out_buf: [u8; 2] = [ Commands::GetStatus as u8, 0x00 ];
+        let mut status = [0u8; 1];
+
+        trace!("wakeup cmd: {:02x?}", out_buf);
+
+        self.try_prefix_read(&out_buf, &mut status).map_err(|e| Error::from(e) )?;
+        self.wait_busy()
+    }
+}
+
+
+/// Wrapper inverting the sense of the BUSY and / or DIO lines, for boards with an inline
+/// inverter or level shifter between the radio and host
+///
+/// This wraps the underlying SPI / pin implementation so all BUSY waits and DIO reads
+/// performed by the `Hal` implementation honour the configured polarity.
+pub struct Polarity<T> {
+    inner: T,
+    busy_active_low: bool,
+    dio_active_low: bool,
+}
+
+impl <T> Polarity<T> {
+    /// Wrap an SPI / pin implementation with the provided BUSY and DIO polarities
+    pub fn new(inner: T, busy_active_low: bool, dio_active_low: bool) -> Self {
+        Self { inner, busy_active_low, dio_active_low }
+    }
+
+    /// Unwrap the underlying implementation
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn invert(s: PinState) -> PinState {
+        match s {
+            PinState::High => PinState::Low,
+            PinState::Low => PinState::High,
+        }
+    }
 }
 
+impl <T: Transactional<u8>> Transactional<u8> for Polarity<T> {
+    type Error = T::Error;
+
+    fn try_exec<'a>(&mut self, operations: &mut [Operation<'a, u8>]) -> Result<(), Self::Error> {
+        self.inner.try_exec(operations)
+    }
+}
+
+impl <T: PrefixRead> PrefixRead for Polarity<T> {
+    type Error = T::Error;
+
+    fn try_prefix_read(&mut self, prefix: &[u8], data: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.try_prefix_read(prefix, data)
+    }
+}
+
+impl <T: PrefixWrite> PrefixWrite for Polarity<T> {
+    type Error = T::Error;
+
+    fn try_prefix_write(&mut self, prefix: &[u8], data: &[u8]) -> Result<(), Self::Error> {
+        self.inner.try_prefix_write(prefix, data)
+    }
+}
+
+impl <T: Reset> Reset for Polarity<T> {
+    type Error = T::Error;
+
+    fn set_reset(&mut self, state: PinState) -> Result<(), Self::Error> {
+        self.inner.set_reset(state)
+    }
+}
+
+impl <T: Busy> Busy for Polarity<T> {
+    type Error = T::Error;
+
+    fn get_busy(&mut self) -> Result<PinState, Self::Error> {
+        let s = self.inner.get_busy()?;
+        Ok(if self.busy_active_low { Self::invert(s) } else { s })
+    }
+}
+
+impl <T: Ready> Ready for Polarity<T> {
+    type Error = T::Error;
+
+    fn get_ready(&mut self) -> Result<PinState, Self::Error> {
+        let s = self.inner.get_ready()?;
+        Ok(if self.dio_active_low { Self::invert(s) } else { s })
+    }
+}
+
+impl <T: DelayMs<u32>> DelayMs<u32> for Polarity<T> {
+    type Error = T::Error;
+
+    fn try_delay_ms(&mut self, ms: u32) -> Result<(), Self::Error> {
+        self.inner.try_delay_ms(ms)
+    }
+}
+
+impl <T: DelayUs<u32>> DelayUs<u32> for Polarity<T> {
+    type Error = T::Error;
+
+    fn try_delay_us(&mut self, us: u32) -> Result<(), Self::Error> {
+        self.inner.try_delay_us(us)
+    }
+}
+
+/// SPI transaction direction for tracing
+#[cfg(feature = "trace")]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SpiDirection {
+    /// Data written to the device
+    Write,
+    /// Data read from the device
+    Read,
+}
+
+/// SPI transaction trace event, see `TracedHal`
+#[cfg(feature = "trace")]
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpiTraceEvent<'a> {
+    /// Command opcode
+    pub opcode: u8,
+    /// Register address or buffer offset, where applicable
+    pub address: Option<u16>,
+    /// Transaction direction
+    pub direction: SpiDirection,
+    /// Data written, or read following completion
+    pub data: &'a [u8],
+}
+
+/// Hal wrapper calling a user provided function for each command, register,
+/// and buffer transaction, for observing device communication during bring-up and testing
+#[cfg(feature = "trace")]
+pub struct TracedHal<H, F> {
+    inner: H,
+    trace: F,
+}
+
+#[cfg(feature = "trace")]
+impl <H, F> TracedHal<H, F>
+where
+    F: FnMut(&SpiTraceEvent),
+{
+    /// Wrap a Hal implementation, calling `trace` for each transaction
+    pub fn new(inner: H, trace: F) -> Self {
+        Self { inner, trace }
+    }
+
+    /// Unwrap the underlying Hal implementation
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+
+    fn emit(&mut self, opcode: u8, address: Option<u16>, direction: SpiDirection, data: &[u8]) {
+        (self.trace)(&SpiTraceEvent{ opcode, address, direction, data })
+    }
+}
+
+#[cfg(feature = "trace")]
+impl<H, F, CommsError, PinError, DelayError> Hal<CommsError, PinError, DelayError> for TracedHal<H, F>
+where
+    H: Hal<CommsError, PinError, DelayError>,
+    F: FnMut(&SpiTraceEvent),
+    CommsError: Debug + Sync + Send,
+    PinError: Debug + Sync + Send,
+    DelayError: Debug + Sync + Send,
+{
+    fn reset(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.reset()
+    }
+
+    fn reset_timed(&mut self, assert_ms: u32, settle_ms: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.reset_timed(assert_ms, settle_ms)
+    }
+
+    fn get_busy(&mut self) -> Result<PinState, Error<CommsError, PinError, DelayError>> {
+        self.inner.get_busy()
+    }
+
+    fn get_dio(&mut self) -> Result<PinState, Error<CommsError, PinError, DelayError>> {
+        self.inner.get_dio()
+    }
+
+    fn try_delay_ms(&mut self, ms: u32) -> Result<(), DelayError> {
+        self.inner.try_delay_ms(ms)
+    }
+
+    fn try_delay_us(&mut self, us: u32) -> Result<(), DelayError> {
+        self.inner.try_delay_us(us)
+    }
+
+    fn write_cmd(&mut self, command: u8, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.emit(command, None, SpiDirection::Write, data);
+        self.inner.write_cmd(command, data)
+    }
+
+    fn read_cmd(&mut self, command: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.read_cmd(command, data)?;
+        self.emit(command, None, SpiDirection::Read, data);
+        Ok(())
+    }
+
+    fn write_cmds(&mut self, cmds: &[(u8, &[u8])]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        for (command, data) in cmds {
+            self.emit(*command, None, SpiDirection::Write, data);
+        }
+        self.inner.write_cmds(cmds)
+    }
+
+    fn write_regs(&mut self, reg: u16, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.emit(Commands::WiteRegister as u8, Some(reg), SpiDirection::Write, data);
+        self.inner.write_regs(reg, data)
+    }
+
+    fn read_regs(&mut self, reg: u16, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.read_regs(reg, data)?;
+        self.emit(Commands::ReadRegister as u8, Some(reg), SpiDirection::Read, data);
+        Ok(())
+    }
+
+    fn write_buff(&mut self, offset: u8, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.emit(Commands::WriteBuffer as u8, Some(offset as u16), SpiDirection::Write, data);
+        self.inner.write_buff(offset, data)
+    }
+
+    fn read_buff(&mut self, offset: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.read_buff(offset, data)?;
+        self.emit(Commands::ReadBuffer as u8, Some(offset as u16), SpiDirection::Read, data);
+        Ok(())
+    }
+
+    fn sleep(&mut self, config: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.emit(Commands::SetSleep as u8, None, SpiDirection::Write, &[config]);
+        self.inner.sleep(config)
+    }
+
+    fn wakeup(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.wakeup()
+    }
+
+    fn wait_busy(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.inner.wait_busy()
+    }
+}
@@ -16,6 +16,17 @@ pub struct BleChannel {
     pub ms: ModShaping,
 }
 
+impl Default for BleChannel {
+    fn default() -> Self {
+        Self {
+            freq: 2_440_000_000,
+            br_bw: GfskBleBitrateBandwidth::BR_1_000_BW_1_2,
+            mi: GfskBleModIndex::MOD_IND_0_50,
+            ms: ModShaping::Bt0_5,
+        }
+    }
+}
+
 /// BLE operating mode packet configuration
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -30,6 +41,17 @@ pub struct BleConfig {
     pub whitening: WhiteningModes,
 }
 
+impl Default for BleConfig {
+    fn default() -> Self {
+        Self {
+            connection_state: BleConnectionStates::BLE_PAYLOAD_LENGTH_MAX_37_BYTES,
+            crc_field: BleCrcFields::BLE_CRC_3B,
+            packet_type: BlePacketTypes::BLE_PRBS_9,
+            whitening: WhiteningModes::RADIO_WHITENING_ON,
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
 pub enum BleConnectionStates {
@@ -7,7 +7,9 @@
 pub enum ModShaping {
     /// No filtering
     Off                  = 0x00,
+    /// Gaussian filter with BT = 1.0
     Bt1_0                = 0x10,
+    /// Gaussian filter with BT = 0.5
     Bt0_5                = 0x20,
 }
 
@@ -1,6 +1,10 @@
 //! Flrc mode device configuration definitions
 
+
+use log::warn;
+
 use super::common::*;
+use crate::ValidationError;
 
 /// FLRC configuration structure
 #[derive(Clone, PartialEq, Debug)]
@@ -9,7 +13,7 @@ pub struct FlrcChannel {
     /// Operating frequency
     pub freq: u32,
     /// Bitrate bandwidth
-    pub br_bw: FlrcBitrate,
+    pub br_bw: FlrcBitrateBandwidth,
     /// Coding rate
     pub cr: FlrcCodingRate,
     /// Modulation shaping
@@ -20,13 +24,31 @@ impl Default for FlrcChannel {
     fn default() -> Self {
         Self {
             freq: 2_440_000_000,
-            br_bw: FlrcBitrate::BR_2_080_BW_2_4,
+            br_bw: FlrcBitrateBandwidth::Br2080Bw2400,
             cr: FlrcCodingRate::Cr3_4,
             ms: ModShaping::Off,
         }   
     }
 }
 
+impl FlrcChannel {
+    /// Validate the bitrate / coding rate combination,
+    /// the 2.6 and 2.08 Mb/s modes support only 1/0 and 3/4 coding rates
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match (self.br_bw, self.cr) {
+            (FlrcBitrateBandwidth::Br2600Bw2400, FlrcCodingRate::Cr1_2)
+            | (FlrcBitrateBandwidth::Br2080Bw2400, FlrcCodingRate::Cr1_2) => {
+                warn!("Unsupported FLRC bitrate / coding rate combination ({:?}, {:?})", self.br_bw, self.cr);
+                Err(ValidationError::InvalidConfiguration)
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Maximum FLRC payload length in bytes
+pub const FLRC_PAYLOAD_MAX: u8 = 127;
+
 /// FLRC packet configuration structure
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -81,6 +103,57 @@ pub enum FlrcBitrate {
     BR_0_260_BW_0_3                    = 0xEB,
 }
 
+/// Bitrate / bandwidth pair for FLRC mode, encoded in the first `SetModulationParams` byte
+///
+/// Only the datasheet-valid combinations are enumerated, named by bitrate (kb/s) and
+/// bandwidth (kHz). `FlrcBitrate` values convert with `From`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum FlrcBitrateBandwidth {
+    /// Baud: 2600 kbps Bandwidth: 2.4 MHz
+    Br2600Bw2400                       = 0x04,
+    /// Baud: 2080 kbps Bandwidth: 2.4 MHz
+    Br2080Bw2400                       = 0x28,
+    /// Baud: 1300 kbps Bandwidth: 1.2 MHz
+    Br1300Bw1200                       = 0x45,
+    /// Baud: 1040 kbps Bandwidth: 1.2 MHz
+    Br1040Bw1200                       = 0x69,
+    /// Baud: 650 kbps Bandwidth: 0.6 MHz
+    Br650Bw600                         = 0x86,
+    /// Baud: 520 kbps Bandwidth: 0.6 MHz
+    Br520Bw600                         = 0xAA,
+    /// Baud: 325 kbps Bandwidth: 0.3 MHz
+    Br325Bw300                         = 0xC7,
+    /// Baud: 260 kbps Bandwidth: 0.3 MHz
+    Br260Bw300                         = 0xEB,
+}
+
+impl From<FlrcBitrate> for FlrcBitrateBandwidth {
+    fn from(br: FlrcBitrate) -> Self {
+        use self::FlrcBitrate::*;
+
+        match br {
+            BR_2_600_BW_2_4 => FlrcBitrateBandwidth::Br2600Bw2400,
+            BR_2_080_BW_2_4 => FlrcBitrateBandwidth::Br2080Bw2400,
+            BR_1_300_BW_1_2 => FlrcBitrateBandwidth::Br1300Bw1200,
+            BR_1_040_BW_1_2 => FlrcBitrateBandwidth::Br1040Bw1200,
+            BR_0_650_BW_0_6 => FlrcBitrateBandwidth::Br650Bw600,
+            BR_0_520_BW_0_6 => FlrcBitrateBandwidth::Br520Bw600,
+            BR_0_325_BW_0_3 => FlrcBitrateBandwidth::Br325Bw300,
+            BR_0_260_BW_0_3 => FlrcBitrateBandwidth::Br260Bw300,
+        }
+    }
+}
+
+#[cfg(feature = "util")]
+impl std::str::FromStr for FlrcBitrateBandwidth {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<FlrcBitrate>().map(Self::from)
+    }
+}
+
 #[cfg(feature = "util")]
 const FLRC_BIT_RATE_PARSE_ERR: &str = "Invalid FLRC bitrate bandwidth (supported options: 2600_2400, 2080_2400, 1300_1200, 1040_1200, 650_600, 520_600, 325_300, 260_300)";
 
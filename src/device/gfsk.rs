@@ -1,7 +1,31 @@
 //! Gfsk mode device configuration definitions
 
+use bitflags::bitflags;
+
 use super::common::*;
 
+/// Gaussian modulation shaping (BT) for GFSK mode, encoded in the third `SetModulationParams` byte
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+pub enum GfskModShaping {
+    /// No filtering
+    Off                  = 0x00,
+    /// Gaussian filter with BT = 1.0
+    Bt1_0                = 0x10,
+    /// Gaussian filter with BT = 0.5
+    Bt0_5                = 0x20,
+}
+
+impl From<ModShaping> for GfskModShaping {
+    fn from(ms: ModShaping) -> Self {
+        match ms {
+            ModShaping::Off => GfskModShaping::Off,
+            ModShaping::Bt1_0 => GfskModShaping::Bt1_0,
+            ModShaping::Bt0_5 => GfskModShaping::Bt0_5,
+        }
+    }
+}
+
 /// GFSK operating mode configuration
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -12,8 +36,8 @@ pub struct GfskChannel {
     pub br_bw: GfskBleBitrateBandwidth,
     /// Modulation index
     pub mi: GfskBleModIndex,
-    /// Modulation shaping
-    pub ms: ModShaping,
+    /// Modulation shaping (defaults to no filtering)
+    pub ms: GfskModShaping,
 }
 
 impl Default for GfskChannel {
@@ -22,7 +46,7 @@ impl Default for GfskChannel {
             freq: 2_440_000_000,
             br_bw: GfskBleBitrateBandwidth::BR_0_250_BW_0_3,
             mi: GfskBleModIndex::MOD_IND_0_35,
-            ms: ModShaping::Off,
+            ms: GfskModShaping::Off,
         }   
     }
 }
@@ -81,3 +105,29 @@ pub enum GfskSyncWordLength {
     /// Sync word length: 5 bytes
     GFSK_SYNCWORD_LENGTH_5_BYTE              = 0x08,
 }
+
+bitflags! {
+    /// Sync word match selection, one flag per programmed sync word (1-3)
+    pub struct GfskSyncMatch: u8 {
+        const SYNCWORD_1 = 0x10;
+        const SYNCWORD_2 = 0x20;
+        const SYNCWORD_3 = 0x40;
+    }
+}
+
+impl From<GfskSyncMatch> for SyncWordRxMatch {
+    fn from(m: GfskSyncMatch) -> Self {
+        use SyncWordRxMatch::*;
+
+        match m.bits() {
+            0x10 => RADIO_RX_MATCH_SYNCWORD_1,
+            0x20 => RADIO_RX_MATCH_SYNCWORD_2,
+            0x30 => RADIO_RX_MATCH_SYNCWORD_1_2,
+            0x40 => RADIO_RX_MATCH_SYNCWORD_3,
+            0x50 => RADIO_RX_MATCH_SYNCWORD_1_3,
+            0x60 => RADIO_RX_MATCH_SYNCWORD_2_3,
+            0x70 => RADIO_RX_MATCH_SYNCWORD_1_2_3,
+            _ => RADIO_RX_MATCH_SYNCWORD_OFF,
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! LoRa device configuration definitions
 
+
+use log::warn;
+
+use crate::ValidationError;
+
 /// LoRa mode radio configuration
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
 pub struct LoRaConfig {
-    // Preamble length in symbols (defaults to 8)
-    pub preamble_length: u8,
+    // Preamble length in symbols (defaults to 8), see `encode_preamble_length`
+    pub preamble_length: u32,
     /// LoRa header configuration, defaults to variable packet length with explicit headers
     pub header_type: LoRaHeader,
     /// Payload length configuration (or maximum length for variable mode)
@@ -14,6 +19,11 @@ pub struct LoRaConfig {
     pub crc_mode: LoRaCrc,
     /// IQ inversion configuration (defaults to disabled)
     pub invert_iq: LoRaIq,
+    /// Explicit header CRC configuration (defaults to enabled)
+    ///
+    /// The SX128x always includes a CRC in explicit LoRa headers, with no `SetPacketParams`
+    /// field to control this, so only `true` is currently supported.
+    pub header_crc: bool,
 }
 
 impl Default for LoRaConfig {
@@ -24,10 +34,51 @@ impl Default for LoRaConfig {
             payload_length: 255,
             crc_mode: LoRaCrc::Enabled,
             invert_iq: LoRaIq::Inverted,
+            header_crc: true,
+        }
+    }
+}
+
+impl LoRaConfig {
+    /// Validate the LoRa packet configuration
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        // Header CRC is fixed by the device and cannot be disabled
+        if !self.header_crc {
+            warn!("Disabling the LoRa header CRC is not supported by the SX128x");
+            return Err(ValidationError::InvalidConfiguration)
         }
+
+        // Preamble must be representable in mantissa / exponent form
+        if self.preamble_length == 0 || self.preamble_length > PREAMBLE_LENGTH_MAX {
+            warn!("Invalid LoRa preamble length: {} (expected 1 to {} symbols)", self.preamble_length, PREAMBLE_LENGTH_MAX);
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        Ok(())
     }
 }
 
+/// Maximum LoRa preamble length in symbols (15 * 2^15)
+pub const PREAMBLE_LENGTH_MAX: u32 = 15 << 15;
+
+/// Encode a LoRa preamble length in symbols to the `SetPacketParams` mantissa / exponent form
+/// (`mantissa * 2^exponent`, with the mantissa in bits 3:0 and exponent in bits 7:4).
+///
+/// Lengths that cannot be represented exactly are rounded up to the next representable length,
+/// and lengths outside of `1..=PREAMBLE_LENGTH_MAX` are clamped.
+pub fn encode_preamble_length(symbols: u32) -> u8 {
+    let symbols = symbols.clamp(1, PREAMBLE_LENGTH_MAX);
+
+    let mut exponent = 0;
+    while (symbols + (1 << exponent) - 1) >> exponent > 0x0F {
+        exponent += 1;
+    }
+
+    let mantissa = (symbols + (1 << exponent) - 1) >> exponent;
+
+    ((exponent as u8) << 4) | mantissa as u8
+}
+
 /// LoRa mode channel configuration
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -53,6 +104,37 @@ impl Default for LoRaChannel {
     }
 }
 
+impl LoRaChannel {
+    /// Compute the LoRa symbol period in microseconds for the channel configuration
+    pub fn symbol_time_us(&self) -> u32 {
+        let chips = 1u64 << (self.sf as u8 >> 4);
+        (chips * 1_000_000 / self.bw.get_bw_hz() as u64) as u32
+    }
+
+    /// Compute the theoretical receiver sensitivity in dBm for the channel configuration,
+    /// from the thermal noise floor, receiver noise figure, and required SNR
+    #[cfg(feature = "float")]
+    pub fn sensitivity_dbm(&self) -> f32 {
+        THERMAL_NOISE_DBM_HZ + self.bw.log_bw_db() + NOISE_FIGURE_DB + self.sf.required_snr()
+    }
+
+    /// Compute the fractional LoRa symbol period in microseconds for the channel configuration
+    #[cfg(feature = "float")]
+    pub fn symbol_duration_us(&self) -> f32 {
+        let chips = (1u32 << (self.sf as u8 >> 4)) as f32;
+        chips * 1_000_000.0 / self.bw.hz() as f32
+    }
+}
+
+/// Thermal noise density at room temperature (dBm/Hz)
+#[cfg(feature = "float")]
+pub const THERMAL_NOISE_DBM_HZ: f32 = -174.0;
+
+/// Effective receiver noise figure in dB (including implementation losses),
+/// fitted to datasheet LoRa sensitivities
+#[cfg(feature = "float")]
+pub const NOISE_FIGURE_DB: f32 = 11.0;
+
 /// Spreading factor for LoRa mode
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -67,6 +149,55 @@ pub enum LoRaSpreadingFactor {
     Sf12  = 0xC0,
 }
 
+impl LoRaSpreadingFactor {
+    /// Fetch the minimum SNR in dB required for demodulation at a given spreading factor
+    #[cfg(feature = "float")]
+    pub fn required_snr(&self) -> f32 {
+        match self {
+            LoRaSpreadingFactor::Sf5 => -2.5,
+            LoRaSpreadingFactor::Sf6 => -5.0,
+            LoRaSpreadingFactor::Sf7 => -7.5,
+            LoRaSpreadingFactor::Sf8 => -10.0,
+            LoRaSpreadingFactor::Sf9 => -12.5,
+            LoRaSpreadingFactor::Sf10 => -15.0,
+            LoRaSpreadingFactor::Sf11 => -17.5,
+            LoRaSpreadingFactor::Sf12 => -20.0,
+        }
+    }
+}
+
+/// Ranging calibration constants for SF5 to SF10 at 400 kHz bandwidth
+pub const RANGING_CALIBRATION_BW400: [u16; 6] = [10299, 10271, 10244, 10242, 10230, 10246];
+/// Ranging calibration constants for SF5 to SF10 at 800 kHz bandwidth
+pub const RANGING_CALIBRATION_BW800: [u16; 6] = [11486, 11474, 11453, 11426, 11417, 11401];
+/// Ranging calibration constants for SF5 to SF10 at 1600 kHz bandwidth
+pub const RANGING_CALIBRATION_BW1600: [u16; 6] = [13308, 13493, 13528, 13515, 13430, 13376];
+
+/// Fetch the ranging calibration constant for a given spreading factor and bandwidth,
+/// returning `None` for combinations not supported in ranging mode
+pub fn ranging_calibration(sf: LoRaSpreadingFactor, bw: LoRaBandwidth) -> Option<u16> {
+    use LoRaSpreadingFactor::*;
+
+    let table = match bw {
+        LoRaBandwidth::Bw400kHz => &RANGING_CALIBRATION_BW400,
+        LoRaBandwidth::Bw800kHz => &RANGING_CALIBRATION_BW800,
+        LoRaBandwidth::Bw1600kHz => &RANGING_CALIBRATION_BW1600,
+        _ => return None,
+    };
+
+    let index = match sf {
+        Sf5 => 0,
+        Sf6 => 1,
+        Sf7 => 2,
+        Sf8 => 3,
+        Sf9 => 4,
+        Sf10 => 5,
+        _ => return None,
+    };
+
+    Some(table[index])
+}
+
 /// Bandwidth for LoRa mode
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -91,6 +222,22 @@ impl LoRaBandwidth {
             LoRaBandwidth::Bw1600kHz => 1625000,
         }
     }
+
+    /// Fetch the bandwidth in dB-Hz (`10 * log10(bw_hz)`), for noise floor calculations
+    #[cfg(feature = "float")]
+    pub fn log_bw_db(&self) -> f32 {
+        match self {
+            LoRaBandwidth::Bw200kHz => 53.077,
+            LoRaBandwidth::Bw400kHz => 56.088,
+            LoRaBandwidth::Bw800kHz => 59.098,
+            LoRaBandwidth::Bw1600kHz => 62.109,
+        }
+    }
+
+    /// Fetch the bandwidth in Hz, alias for `get_bw_hz`
+    pub fn hz(&self) -> u32 {
+        self.get_bw_hz()
+    }
 }
 
 /// Coding rates for LoRa mode
@@ -127,6 +274,38 @@ pub enum LoRaIq {
     Inverted = 0x00,
 }
 
+/// Number of symbols used for LoRa channel activity detection
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+pub enum LoRaCadSymbols {
+    Cad01Symbol  = 0x00,
+    Cad02Symbols = 0x20,
+    Cad04Symbols = 0x40,
+    Cad08Symbols = 0x60,
+    Cad16Symbols = 0x80,
+}
+
+/// Device behaviour on completion of LoRa channel activity detection
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+pub enum CadExitMode {
+    /// Return to STDBY_RC
+    StandbyRc,
+    /// Enter receive mode when activity is detected, otherwise return to STDBY_RC
+    Rx,
+}
+
+/// Channel activity detection results, see `Sx128x::poll_cad`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CadEvent {
+    /// No activity detected
+    Clear,
+    /// Activity detected
+    Detected,
+    /// Activity detected and receive mode entered (`CadExitMode::Rx`)
+    Receiving,
+}
+
 /// Header configuration for LoRa packet types
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
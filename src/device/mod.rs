@@ -1,8 +1,11 @@
 #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]
 
-use log::{error};
+
+use log::{error, warn};
 use bitflags::bitflags;
 
+use crate::{ValidationError, FREQ_MIN, FREQ_MAX};
+
 
 pub mod ble;
 use ble::{BleChannel, BleConfig};
@@ -18,9 +21,77 @@ pub mod common;
 
 pub const BUSY_TIMEOUT_MS: u32 = 500;
 
+/// States in which modem, channel, and buffer configuration may be safely updated
+pub const CONFIG_STATES: [State; 3] = [State::StandbyRc, State::StandbyXosc, State::Fs];
+
+/// Maximum random backoff in LoRa symbol periods between CSMA channel activity checks
+pub const CSMA_BACKOFF_SYMBOLS_MAX: u8 = 16;
+
+/// Maximum time to wait for the PLL to lock on entering FS mode
+pub const FS_LOCK_TIMEOUT_MS: u32 = 5;
+
+/// Results of `Sx128x::self_test`, with a flag for each check
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SelfTestReport {
+    /// Firmware version read from the device
+    pub firmware_version: u16,
+    /// Firmware version is listed in `Config::accepted_firmware_versions`
+    pub firmware: bool,
+    /// Scratch register write and read-back matched
+    pub readback: bool,
+    /// Device entered STDBY_XOSC without reporting a command error
+    pub xosc: bool,
+    /// BUSY was de-asserted with the device idle
+    pub busy: bool,
+}
+
+impl SelfTestReport {
+    /// Check whether all self test checks passed
+    pub fn passed(&self) -> bool {
+        self.firmware && self.readback && self.xosc && self.busy
+    }
+}
+
+/// Radio activity events, reported via `Sx128x::set_activity_hook`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Activity {
+    /// Transmission started
+    TxStart,
+    /// Transmission completed
+    TxDone,
+    /// Receive mode entered
+    RxStart,
+    /// Packet received
+    RxDone,
+}
+
+/// Known valid device firmware versions
+pub const FIRMWARE_VERSIONS: &[u16] = &[0xA9B5];
+
+/// Behaviour where a responding device reports a firmware version not listed in
+/// `Config::accepted_firmware_versions`
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum VersionMismatchPolicy {
+    /// Log at error level and fail initialisation with `Error::InvalidDevice`
+    Error,
+    /// Log at error level and continue initialisation
+    Warn,
+    /// Continue initialisation
+    Ignore,
+}
+
+#[cfg(feature = "serde")]
+fn default_firmware_versions() -> &'static [u16] {
+    FIRMWARE_VERSIONS
+}
+
 /// Sx128x general configuration object
+///
+/// Fields missing from serialized configurations take their `Config::default()` values.
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Config {
     /// Regulator mode configuration
     pub regulator_mode: RegulatorMode,
@@ -48,17 +119,106 @@ pub struct Config {
     /// further details
     pub rf_timeout: Timeout,
 
+    /// TX timeout configuration, overriding `rf_timeout` for transmit operations where set
+    pub tx_timeout: Option<Timeout>,
+    /// RX timeout configuration, overriding `rf_timeout` for receive operations where set
+    pub rx_timeout: Option<Timeout>,
+
     /// Crystal oscillator frequency
     pub xtal_freq: u32,
 
+    /// Measured static crystal frequency error in parts per billion (positive where the crystal runs fast),
+    /// applied to all frequency conversions so programmed frequencies are pre-compensated.
+    /// See `Sx128x::set_frequency_compensated` for dynamic (temperature) compensation.
+    ///
+    /// This is an integer in ppb rather than a floating point ppm value so compensation does not
+    /// depend on the `float` feature (or an FPU), multiply ppm by 1000 (eg. 2.5 ppm is 2500).
+    pub xtal_ppb_offset: i32,
+
     /// Timeout for blocking / polling internal methods
     pub timeout_ms: u32,
 
     /// Skip firmware version validation
+    #[deprecated(note = "use `version_mismatch_policy: VersionMismatchPolicy::Ignore`")]
     pub skip_version_check: bool,
+
+    /// Behaviour on firmware version mismatch (defaults to `VersionMismatchPolicy::Error`)
+    pub version_mismatch_policy: VersionMismatchPolicy,
+
+    /// Firmware versions accepted on initialisation (defaults to `FIRMWARE_VERSIONS`)
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_firmware_versions"))]
+    pub accepted_firmware_versions: &'static [u16],
+
+    /// Skip device calibration on initialisation
+    pub skip_calibration: bool,
+
+    /// Reset (NRESET) assertion time in milliseconds
+    pub reset_assert_ms: u32,
+
+    /// Post-reset settling time in milliseconds, prior to communicating with the device
+    pub reset_settle_ms: u32,
+
+    /// Maximum number of bytes per SPI buffer transfer,
+    /// longer buffer reads / writes are split into chunks of this size
+    /// to support HALs with limited transfer lengths
+    pub max_transfer: usize,
+
+    /// Enable high sensitivity (boosted gain) receive mode,
+    /// improving sensitivity by ~3dB at the cost of increased receive current
+    pub rx_boosted: bool,
+
+    /// Reject out-of-range TX power requests rather than clamping them
+    pub strict_power: bool,
+
+    /// Verify the device packet type following modem configuration
+    pub verify: bool,
+
+    /// Number of times to retry critical commands reporting a failed command status
+    pub command_retries: usize,
+
+    /// Explicit TX and RX buffer base addresses (see `Sx128x::set_buffer_base`), written on
+    /// configuration, `None` resets both to 0 on each transmit or receive (clearing any buffer layout)
+    pub buffer_base: Option<(u8, u8)>,
+
+    /// Allow configuration and buffer writes outside of standby and FS states,
+    /// skipping the state checks (and associated status reads) prior to these operations
+    pub unsafe_state_transitions: bool,
+
+    /// Put the device to sleep (best-effort) when the driver is dropped
+    pub power_down_on_drop: bool,
+
+    /// Minimum RX gain / AGC settle time in microseconds after entering receive mode,
+    /// applied prior to the first RSSI measurement (defaults to 0, disabled)
+    pub rx_settle_us: u32,
+
+    /// Re-apply modem and buffer configuration on every receive (defaults to true)
+    ///
+    /// When disabled this is skipped where no modem or buffer configuration has been written
+    /// through the driver since the last receive. Raw register or command writes are not tracked.
+    pub reconfigure_on_rx: bool,
+
+    /// Standby state entered on initialisation and configuration, either `State::StandbyRc`
+    /// (lower power) or `State::StandbyXosc` (faster transitions), defaults to `StandbyRc`
+    pub initial_standby: State,
+
+    /// BUSY line is active low (ie. inverted by a level shifter), defaults to false
+    ///
+    /// This applies only at construction with `Sx128x::spi_polarity` (`Sx128x::spi` rejects it),
+    /// and is not re-applied by `Sx128x::configure`. Other `Hal` implementations may be wrapped
+    /// with `base::Polarity` to the same effect.
+    pub busy_active_low: bool,
+
+    /// DIO (IRQ) line is active low, defaults to false, see `busy_active_low`
+    pub dio_active_low: bool,
+
+    /// Number of times to retry reset and device detection in `Sx128x::new` where the
+    /// device does not respond (eg. on boards with slowly ramping supplies), with the
+    /// settle time extended by `reset_settle_ms` on each retry
+    pub reset_retries: u8,
 }
 
 impl Default for Config {
+    #[allow(deprecated)]
     fn default() -> Self {
         Config{
             regulator_mode: RegulatorMode::Ldo,
@@ -68,9 +228,31 @@ impl Default for Config {
             channel: Channel::LoRa(LoRaChannel::default()),
             //timeout: Timeout::Configurable{ step: TickSize::TickSize1000us, count: 1000 },
             rf_timeout: Timeout::Single,
+            tx_timeout: None,
+            rx_timeout: None,
             xtal_freq: 52000000,
+            xtal_ppb_offset: 0,
             timeout_ms: 100,
             skip_version_check: false,
+            version_mismatch_policy: VersionMismatchPolicy::Error,
+            accepted_firmware_versions: FIRMWARE_VERSIONS,
+            skip_calibration: false,
+            reset_assert_ms: 50,
+            reset_settle_ms: 20,
+            max_transfer: 255,
+            rx_boosted: false,
+            strict_power: false,
+            verify: false,
+            command_retries: crate::NUM_RETRIES,
+            buffer_base: None,
+            unsafe_state_transitions: false,
+            power_down_on_drop: false,
+            rx_settle_us: 0,
+            reconfigure_on_rx: true,
+            initial_standby: State::StandbyRc,
+            busy_active_low: false,
+            dio_active_low: false,
+            reset_retries: 1,
         }
     }
 }
@@ -105,18 +287,293 @@ impl Config {
             ..Default::default()
         }
     }
+
+    /// Create a default BLE configuration
+    pub fn ble() -> Self {
+        Config{
+            packet_type: PacketType::Ble,
+            modem: Modem::Ble(BleConfig::default()),
+            channel: Channel::Ble(BleChannel::default()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Config {
+    /// Validate the configuration in software, prior to any device communication
+    ///
+    /// This checks modem and channel pairing, frequency bounds, TX power range
+    /// (where `strict_power` is set), and sync word configuration.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        // Check modem and channel configurations match
+        match (&self.modem, &self.channel) {
+            (Modem::LoRa(_), Channel::LoRa(_)) => (),
+            (Modem::Flrc(_), Channel::Flrc(_)) => (),
+            (Modem::Gfsk(_), Channel::Gfsk(_)) => (),
+            (Modem::Ble(_), Channel::Ble(_)) => (),
+            (Modem::Ranging(_), Channel::Ranging(_)) => (),
+            _ => {
+                warn!("Mismatched modem and channel configurations (modem: {:?}, channel: {:?})", self.modem, self.channel);
+                return Err(ValidationError::InvalidConfiguration)
+            }
+        }
+
+        // Check frequency is in range
+        let freq = self.channel.frequency();
+        if !(FREQ_MIN..=FREQ_MAX).contains(&freq) {
+            warn!("Frequency {} Hz out of range ({} to {} Hz)", freq, FREQ_MIN, FREQ_MAX);
+            return Err(ValidationError::InvalidFrequency)
+        }
+
+        // Check TX power is in range
+        let power = self.pa_config.power;
+        if self.strict_power && !(TX_POWER_MIN..=TX_POWER_MAX).contains(&power) {
+            warn!("TX power {} dBm out of range ({} to {} dBm)", power, TX_POWER_MIN, TX_POWER_MAX);
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        // Check FLRC modulation and sync word configuration
+        if let Channel::Flrc(c) = &self.channel {
+            c.validate()?;
+        }
+
+        // Check fixed length packets have a non-zero length
+        let fixed_len = match &self.modem {
+            Modem::Gfsk(c) if c.header_type == common::GfskFlrcPacketLength::Fixed => Some(c.payload_length),
+            Modem::Flrc(c) if c.header_type == common::GfskFlrcPacketLength::Fixed => Some(c.payload_length),
+            _ => None,
+        };
+        if fixed_len == Some(0) {
+            warn!("Fixed length packets require a non-zero payload length");
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        // Check LoRa packet configuration
+        if let Modem::LoRa(c) | Modem::Ranging(c) = &self.modem {
+            c.validate()?;
+        }
+
+        if let Modem::Flrc(c) = &self.modem {
+            if c.sync_word_length == flrc::FlrcSyncWordLength::None && c.sync_word_match != common::SyncWordRxMatch::RADIO_RX_MATCH_SYNCWORD_OFF {
+                warn!("FLRC sync word matching enabled ({:?}) without a sync word", c.sync_word_match);
+                return Err(ValidationError::InvalidConfiguration)
+            }
+        }
+
+        // Check initial state is a standby mode
+        if !matches!(self.initial_standby, State::StandbyRc | State::StandbyXosc) {
+            warn!("Invalid initial standby state: {:?}", self.initial_standby);
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        Ok(())
+    }
 }
 
 impl Config {
     /// Calculate frequency step for a given crystal frequency
     pub fn freq_step(&self) -> f32 {
-        self.xtal_freq as f32 / (2u32 << 17) as f32
+        self.xtal_freq as f32 * (1.0 + self.xtal_ppb_offset as f32 / 1_000_000_000.0) / (2u32 << 17) as f32
     }
 
     /// Convert a provided frequency into configuration steps
+    ///
+    /// This uses floating point and loses precision across the band, see
+    /// `freq_to_steps_u64` for exact conversion when tuning the device.
     pub fn freq_to_steps(&self, f: f32) -> f32 {
         f / self.freq_step() as f32
     }
+
+    /// Convert a provided frequency in Hz into configuration steps using integer math,
+    /// rounding to the nearest step
+    pub fn freq_to_steps_u64(&self, f_hz: u32) -> u32 {
+        let xtal = self.xtal_freq_millihz();
+        ((f_hz as u64 * (2u64 << 17) * 1_000 + xtal / 2) / xtal) as u32
+    }
+
+    /// Convert a provided frequency in Hz into configuration steps, returning
+    /// `ValidationError::InvalidFrequency` where the step count falls outside of the
+    /// `FREQ_MIN` to `FREQ_MAX` band (or the 24-bit `SetRfFrequency` field)
+    pub fn freq_to_steps_checked(&self, f_hz: u32) -> Result<u32, ValidationError> {
+        let steps = self.freq_to_steps_u64(f_hz);
+        let (min, max) = (self.freq_to_steps_u64(FREQ_MIN), self.freq_to_steps_u64(FREQ_MAX));
+
+        if steps < min || steps > max || steps > crate::FREQ_STEPS_MAX {
+            warn!("Frequency {} Hz ({} steps) out of band ({} to {} steps)", f_hz, steps, min, max);
+            return Err(ValidationError::InvalidFrequency)
+        }
+
+        Ok(steps)
+    }
+
+    /// Convert configuration steps back into a frequency in Hz
+    pub fn steps_to_freq(&self, steps: u32) -> u32 {
+        (steps as u64 * self.xtal_freq_millihz() / ((2u64 << 17) * 1_000)) as u32
+    }
+
+    /// Fetch the crystal frequency in millihertz, corrected for `xtal_ppb_offset`
+    fn xtal_freq_millihz(&self) -> u64 {
+        let xtal = self.xtal_freq as i64;
+        (xtal * 1_000 + xtal * self.xtal_ppb_offset as i64 / 1_000_000) as u64
+    }
+}
+
+/// Saved device register context, for restoring configuration following
+/// sleep without data retention
+#[derive(Clone, PartialEq, Debug)]
+pub struct RegContext {
+    /// Driver configuration (modulation, packet, frequency and PA settings)
+    pub config: Config,
+
+    /// Sync word registers
+    pub sync_words: [[u8; 5]; 3],
+    /// Sync word tolerance register
+    pub sync_word_tolerance: u8,
+    /// CRC seed registers
+    pub crc_seed: [u8; 2],
+    /// CRC polynomial registers
+    pub crc_poly: [u8; 2],
+    /// Whitening seed register
+    pub whitening_seed: u8,
+
+    /// LNA regime register
+    pub lna_regime: u8,
+    /// Manual gain control, demodulator detection, and manual gain value registers
+    pub gain_control: [u8; 3],
+}
+
+/// Size of the shared TX / RX data buffer in bytes
+pub const BUFFER_SIZE: usize = 256;
+
+/// TX and RX regions within the shared data buffer, see `Sx128x::set_buffer_layout`
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct BufferLayout {
+    /// TX buffer base address
+    pub tx_base: u8,
+    /// Maximum TX packet length
+    pub tx_len: u8,
+    /// RX buffer base address
+    pub rx_base: u8,
+    /// Maximum RX packet length
+    pub rx_len: u8,
+}
+
+impl BufferLayout {
+    /// Validate that the TX and RX regions fit within the buffer without overlapping
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let tx = self.tx_base as usize..self.tx_base as usize + self.tx_len as usize;
+        let rx = self.rx_base as usize..self.rx_base as usize + self.rx_len as usize;
+
+        if tx.end > BUFFER_SIZE || rx.end > BUFFER_SIZE {
+            error!("Buffer layout {:?} exceeds {} byte buffer", self, BUFFER_SIZE);
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        if !tx.is_empty() && !rx.is_empty() && tx.start < rx.end && rx.start < tx.end {
+            error!("Buffer layout {:?} has overlapping TX and RX regions", self);
+            return Err(ValidationError::InvalidConfiguration)
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert an RSSI in dBm to the device `-2 × raw` encoding, returning None if out of range
+pub fn rssi_to_raw(dbm: i16) -> Option<u8> {
+    if !(-127..=0).contains(&dbm) {
+        return None
+    }
+
+    Some((-dbm * 2) as u8)
+}
+
+/// Receive events, see `Sx128x::poll_receive`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RxEvent {
+    /// Preamble detected
+    PreambleDetected,
+    /// Valid header received
+    HeaderValid,
+    /// Packet received
+    Done,
+    /// Packet received with invalid CRC
+    CrcError,
+    /// Invalid header received (LoRa mode)
+    HeaderError,
+    /// Receive timeout with no preamble detected
+    PreambleTimeout,
+    /// Receive timeout after preamble detection
+    Timeout,
+}
+
+/// RSSI statistics over a number of samples
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RssiStats {
+    /// Mean RSSI in dBm
+    pub mean: i16,
+    /// Minimum RSSI in dBm
+    pub min: i16,
+    /// Maximum RSSI in dBm
+    pub max: i16,
+}
+
+/// Maximum number of packets held in the driver receive queue
+pub const RX_QUEUE_LEN: usize = 8;
+
+/// Queue of received packets held in the device buffer,
+/// see `Sx128x::start_receive_nonclearing`
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct RxQueue {
+    /// Buffer (pointer, length) for each queued packet
+    pub packets: [(u8, u8); RX_QUEUE_LEN],
+    /// Packet information for each queued packet
+    pub info: [PacketInfo; RX_QUEUE_LEN],
+    /// Number of queued packets
+    pub len: usize,
+    /// Receive buffer base address for the next packet
+    pub next: u8,
+    /// Set when a packet has been received and not yet read or queued
+    pub pending: bool,
+}
+
+/// Frequency hopping table, caching `SetRfFrequency` data for a set of channels
+/// to avoid recomputing PLL steps on each hop
+#[derive(Clone, PartialEq, Debug)]
+pub struct HopTable<const N: usize> {
+    steps: [[u8; 3]; N],
+}
+
+impl <const N: usize> HopTable<N> {
+    /// Precompute a hop table for the provided frequencies (in Hz)
+    ///
+    /// Returns `ValidationError::InvalidFrequency` where any frequency is out of band,
+    /// as for `Sx128x::set_frequency`.
+    pub fn new(config: &Config, freqs: &[u32; N]) -> Result<Self, ValidationError> {
+        let mut steps = [[0u8; 3]; N];
+
+        for (s, f) in steps.iter_mut().zip(freqs.iter()) {
+            let c = config.freq_to_steps_checked(*f)?;
+            *s = [(c >> 16) as u8, (c >> 8) as u8, c as u8];
+        }
+
+        Ok(HopTable{ steps })
+    }
+
+    /// Fetch cached `SetRfFrequency` data for the provided index
+    pub fn get(&self, index: usize) -> Option<&[u8; 3]> {
+        self.steps.get(index)
+    }
+
+    /// Fetch the number of channels in the hop table
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    /// Check whether the hop table is empty
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
 }
 
 
@@ -186,6 +643,31 @@ impl Channel {
             Ranging(c) => c.freq,
         }
     }
+
+    /// Update the frequency for a given modulation configuration
+    pub fn set_frequency(&mut self, freq: u32) {
+        use Channel::*;
+
+        match self {
+            Gfsk(c) => c.freq = freq,
+            LoRa(c) => c.freq = freq,
+            Flrc(c) => c.freq = freq,
+            Ble(c) => c.freq = freq,
+            Ranging(c) => c.freq = freq,
+        }
+    }
+
+    /// Fetch `SetModulationParams` command data for a given modulation configuration
+    pub fn modulation_params(&self) -> [u8; 3] {
+        use Channel::*;
+
+        match self {
+            Gfsk(c) => [c.br_bw as u8, c.mi as u8, c.ms as u8],
+            LoRa(c) | Ranging(c) => [c.sf as u8, c.bw as u8, c.cr as u8],
+            Flrc(c) => [c.br_bw as u8, c.cr as u8, c.ms as u8],
+            Ble(c) => [c.br_bw as u8, c.mi as u8, c.ms as u8],
+        }
+    }
 }
 
 impl From<&Channel> for PacketType {
@@ -309,6 +791,34 @@ impl Default for PacketInfo {
     }
 }
 
+#[cfg(feature = "float")]
+impl PacketInfo {
+    /// Compute link budget metrics for a received packet given the transmit power,
+    /// and spreading factor for LoRa packets
+    pub fn link_budget(&self, tx_power_dbm: i8, sf: Option<lora::LoRaSpreadingFactor>) -> LinkBudget {
+        let snr_margin = match (self.snr, sf) {
+            (Some(snr), Some(sf)) => Some(snr as f32 - sf.required_snr()),
+            _ => None,
+        };
+
+        LinkBudget {
+            path_loss: tx_power_dbm as i16 - self.rssi,
+            snr_margin,
+        }
+    }
+}
+
+/// Link budget metrics for a received packet
+#[cfg(feature = "float")]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LinkBudget {
+    /// Path loss in dB (transmit power less received RSSI)
+    pub path_loss: i16,
+    /// LoRa demodulation margin in dB (received SNR less the required SNR for the spreading factor),
+    /// `None` where SNR is not available
+    pub snr_margin: Option<f32>,
+}
+
 /// Regulator operating mode
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -317,8 +827,40 @@ pub enum RegulatorMode {
     Ldo  = 0x00,
     /// Internal DC/DC converter
     Dcdc = 0x01,
+    /// Automatically select DC/DC for high TX power configurations, LDO otherwise
+    ///
+    /// (note this requires the DC/DC inductor to be fitted)
+    Auto,
+}
+
+/// TX power at or above which `RegulatorMode::Auto` selects the DC/DC converter
+pub const REGULATOR_DCDC_POWER_DBM: i8 = 10;
+
+impl RegulatorMode {
+    /// Resolve the regulator mode to be written to the device for the provided TX power
+    pub fn resolve(&self, power: i8) -> RegulatorMode {
+        match self {
+            RegulatorMode::Auto if power >= REGULATOR_DCDC_POWER_DBM => RegulatorMode::Dcdc,
+            RegulatorMode::Auto => RegulatorMode::Ldo,
+            r => *r,
+        }
+    }
 }
 
+/// Receiver LNA gain control mode
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+pub enum GainMode {
+    /// Automatic gain control (default)
+    #[default]
+    Auto,
+    /// Fixed manual gain step (1 to 13, where 13 is maximum gain)
+    Manual(u8),
+}
+
+/// Maximum manual LNA gain step
+pub const MANUAL_GAIN_MAX: u8 = 13;
+
 /// Power amplifier ramp time
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -356,6 +898,7 @@ pub enum PacketType {
 /// Radio commands
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+#[non_exhaustive]
 pub enum Commands {
     GetStatus                = 0xC0,
     WiteRegister             = 0x18,
@@ -398,6 +941,7 @@ pub enum Commands {
 /// Radio registers
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
+#[non_exhaustive]
 pub enum Registers {
     LrFirmwareVersionMsb               = 0x0153,
     LrCrcSeedBaseAddr                  = 0x09C8,
@@ -428,16 +972,31 @@ pub enum Registers {
     ManualGainValue                    = 0x089E,
 }
 
+/// Register used for write / read-back in `Sx128x::self_test` (restored after use)
+pub const SELF_TEST_REGISTER: Registers = Registers::LrSyncWordBaseAddress1;
+
 pub const MASK_RANGINGMUXSEL: u8       = 0xCF;
 pub const MASK_LNA_REGIME: u8          = 0xC0;
 pub const MASK_MANUAL_GAIN_CONTROL: u8 = 0x80;
 pub const MASK_DEMOD_DETECTION: u8     = 0xFE;
 pub const MASK_MANUAL_GAIN_VALUE: u8   = 0xF0;
+pub const MASK_SYNC_WORD_TOLERANCE: u8 = 0x0F;
+
+/// Maximum GFSK sync word bit error tolerance, see `Sx128x::set_gfsk_sync_tolerance`
+pub const SYNC_WORD_TOLERANCE_MAX: u8 = 7;
 
 pub const MASK_LR_ESTIMATED_FREQUENCY_ERROR: u32 = 0x0FFFFF;
 
 pub const AUTO_RX_TX_OFFSET: u16 = 33;
 
+/// Frequency change above which ADC and PLL calibration is re-run
+pub const RECALIBRATION_DELTA_HZ: u32 = 20_000_000;
+
+/// Minimum TX power in dBm
+pub const TX_POWER_MIN: i8 = -18;
+/// Maximum TX power in dBm
+pub const TX_POWER_MAX: i8 = 13;
+
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
 pub enum AutoTx {
@@ -469,6 +1028,34 @@ bitflags! {
     }
 }
 
+impl Irq {
+    /// Flag names in bit order, see `Irq::active_names`
+    const NAMES: [(Irq, &'static str); 16] = [
+        (Irq::TX_DONE, "TX_DONE"),
+        (Irq::RX_DONE, "RX_DONE"),
+        (Irq::SYNCWORD_VALID, "SYNCWORD_VALID"),
+        (Irq::SYNCWORD_ERROR, "SYNCWORD_ERROR"),
+        (Irq::HEADER_VALID, "HEADER_VALID"),
+        (Irq::HEADER_ERROR, "HEADER_ERROR"),
+        (Irq::CRC_ERROR, "CRC_ERROR"),
+        (Irq::RANGING_SLAVE_RESPONSE_DONE, "RANGING_SLAVE_RESPONSE_DONE"),
+        (Irq::RANGING_SLAVE_REQUEST_DISCARDED, "RANGING_SLAVE_REQUEST_DISCARDED"),
+        (Irq::RANGING_MASTER_RESULT_VALID, "RANGING_MASTER_RESULT_VALID"),
+        (Irq::RANGING_MASTER_RESULT_TIMEOUT, "RANGING_MASTER_RESULT_TIMEOUT"),
+        (Irq::RANGING_SLAVE_REQUEST_VALID, "RANGING_SLAVE_REQUEST_VALID"),
+        (Irq::CAD_DONE, "CAD_DONE"),
+        (Irq::CAD_ACTIVITY_DETECTED, "CAD_ACTIVITY_DETECTED"),
+        (Irq::RX_TX_TIMEOUT, "RX_TX_TIMEOUT"),
+        (Irq::PREAMBLE_DETECTED, "PREAMBLE_DETECTED"),
+    ];
+
+    /// Iterate over the names of set flags, in bit order
+    pub fn active_names(&self) -> impl Iterator<Item = &'static str> {
+        let irq = *self;
+        Self::NAMES.iter().filter(move |(f, _)| irq.contains(*f) ).map(|(_, n)| *n )
+    }
+}
+
 /// DIO IRQ flag mask
 pub type DioMask = Irq;
 
@@ -488,6 +1075,101 @@ bitflags! {
 }
 
 
+impl PacketStatus {
+    /// Sync word (or address) error
+    pub fn sync_error(&self) -> bool {
+        self.contains(PacketStatus::SYNC_ERROR)
+    }
+
+    /// Received length exceeded the configured maximum payload length
+    pub fn length_error(&self) -> bool {
+        self.contains(PacketStatus::LENGTH_ERROR)
+    }
+
+    /// Payload CRC check failed
+    pub fn crc_error(&self) -> bool {
+        self.contains(PacketStatus::CRC_ERROR)
+    }
+
+    /// Packet reception was aborted
+    pub fn abort_error(&self) -> bool {
+        self.contains(PacketStatus::ABORT_ERROR)
+    }
+
+    /// Header received
+    pub fn header_received(&self) -> bool {
+        self.contains(PacketStatus::HEADER_RECEIVED)
+    }
+
+    /// Packet reception complete
+    pub fn packet_received(&self) -> bool {
+        self.contains(PacketStatus::PACKET_RECEIVED)
+    }
+
+    /// Packet controller busy
+    pub fn busy(&self) -> bool {
+        self.contains(PacketStatus::PACKET_CONTROLER_BUSY)
+    }
+}
+
+impl core::fmt::Display for PacketStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let flags = [
+            (self.sync_error(), "sync_error"),
+            (self.length_error(), "length_error"),
+            (self.crc_error(), "crc_error"),
+            (self.abort_error(), "abort_error"),
+            (self.header_received(), "header_received"),
+            (self.packet_received(), "packet_received"),
+            (self.busy(), "busy"),
+        ];
+
+        write_flags(f, &flags)
+    }
+}
+
+impl TxRxStatus {
+    /// Packet transmission complete
+    pub fn is_pkt_sent(&self) -> bool {
+        self.contains(TxRxStatus::PACKET_SENT)
+    }
+
+    /// No acknowledgement received (BLE mode)
+    pub fn rx_no_ack(&self) -> bool {
+        self.contains(TxRxStatus::RX_NO_ACK)
+    }
+}
+
+impl core::fmt::Display for TxRxStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let flags = [
+            (self.is_pkt_sent(), "pkt_sent"),
+            (self.rx_no_ack(), "rx_no_ack"),
+        ];
+
+        write_flags(f, &flags)
+    }
+}
+
+/// Write the names of set flags separated by `|`, or `none` if no flags are set
+fn write_flags(f: &mut core::fmt::Formatter<'_>, flags: &[(bool, &str)]) -> core::fmt::Result {
+    let mut first = true;
+
+    for (_, name) in flags.iter().filter(|(set, _)| *set) {
+        if !first {
+            write!(f, "|")?;
+        }
+        write!(f, "{}", name)?;
+        first = false;
+    }
+
+    if first {
+        write!(f, "none")?;
+    }
+
+    Ok(())
+}
+
 bitflags! {
     /// TxRx status packet status byte
     pub struct TxRxStatus: u8 {
@@ -505,6 +1187,16 @@ bitflags! {
 }
 
 
+bitflags! {
+    /// Sleep mode configuration, selects the memories retained while asleep
+    pub struct SleepConfig: u8 {
+        /// Retain the data RAM (device configuration) during sleep
+        const RETAIN_DATA_RAM       = (1 << 0);
+        /// Retain the data buffer contents during sleep
+        const RETAIN_DATA_BUFFER    = (1 << 1);
+    }
+}
+
 bitflags! {
     /// Radio calibration parameters
     pub struct CalibrationParams: u8 {
@@ -517,6 +1209,33 @@ bitflags! {
     }
 }
 
+impl CalibrationParams {
+    /// Create an empty calibration set, for use with the `with_` builder methods
+    pub fn none() -> Self {
+        Self::empty()
+    }
+
+    /// Include ADC (bulk P, bulk N and pulse) calibration
+    pub fn with_adc(self) -> Self {
+        self | Self::ADCBulkPEnable | Self::ADCBulkNEnable | Self::ADCPulseEnable
+    }
+
+    /// Include PLL calibration
+    pub fn with_pll(self) -> Self {
+        self | Self::PLLEnable
+    }
+
+    /// Include 13 MHz RC oscillator calibration
+    pub fn with_rc13m(self) -> Self {
+        self | Self::RC13MEnable
+    }
+
+    /// Include 64 kHz RC oscillator calibration
+    pub fn with_rc64k(self) -> Self {
+        self | Self::RC64KEnable
+    }
+}
+
 /// Ranging mode role
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -541,6 +1260,21 @@ pub enum TickSize {
     TickSize4000us   = 0x03,
 }
 
+impl TickSize {
+    /// Fetch the tick period in nanoseconds
+    pub fn period_ns(&self) -> u64 {
+        match self {
+            TickSize::TickSize0015us => 15_625,
+            TickSize::TickSize0062us => 62_500,
+            TickSize::TickSize1000us => 1_000_000,
+            TickSize::TickSize4000us => 4_000_000,
+        }
+    }
+}
+
+/// Maximum configurable timeout step count (`0xFFFF` selects continuous mode)
+pub const TIMEOUT_COUNT_MAX: u16 = 0xFFFE;
+
 /// Timeout confguration for autonomous radio operations
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))] 
@@ -576,4 +1310,36 @@ impl Timeout {
             Timeout::Continuous      => 0xFFFF,
         }
     }
+
+    /// Create a configurable timeout for the provided duration in microseconds
+    ///
+    /// This selects the finest step size for which the step count fits, preserving resolution
+    /// for short timeouts, rounding the count up so the timeout is never shorter than requested.
+    /// Returns `ValidationError::InvalidConfiguration` for zero durations or durations exceeding
+    /// `TIMEOUT_COUNT_MAX` steps of 4 ms.
+    pub fn from_micros(us: u32) -> Result<Self, ValidationError> {
+        let steps = [TickSize::TickSize0015us, TickSize::TickSize0062us, TickSize::TickSize1000us, TickSize::TickSize4000us];
+        let ns = us as u64 * 1_000;
+
+        for step in steps.iter() {
+            let period = step.period_ns();
+            let count = ns.div_ceil(period);
+
+            if count > 0 && count <= TIMEOUT_COUNT_MAX as u64 {
+                return Ok(Timeout::Configurable{ step: *step, count: count as u16 })
+            }
+        }
+
+        warn!("Invalid timeout: {} us", us);
+        Err(ValidationError::InvalidConfiguration)
+    }
+
+    /// Encode a timeout configuration as `SetTx` / `SetRx` command data
+    pub fn data(&self) -> [u8; 3] {
+        [
+            self.step() as u8,
+            (( self.count() >> 8 ) & 0x00FF ) as u8,
+            (self.count() & 0x00FF ) as u8,
+        ]
+    }
 }
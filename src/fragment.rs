@@ -0,0 +1,111 @@
+//! Long packet fragmentation and reassembly
+//!
+//! Messages longer than a single device packet are split into fragments of up to
+//! `FRAGMENT_MAX` bytes (or the maximum packet length for the current modem configuration),
+//! each prefixed with a three byte header containing the fragment index, the total number
+//! of fragments, and the fragment payload length, allowing padding to be discarded in
+//! fixed length modes. This is a driver convenience rather than a device feature, see
+//! `Sx128x::transmit_long` and `Sx128x::receive_long`.
+
+
+use log::warn;
+
+use crate::ValidationError;
+
+/// Fragment header length (index, count, and payload length)
+pub const FRAGMENT_HEADER_LEN: usize = 3;
+
+/// Maximum fragment length, including header
+pub const FRAGMENT_MAX: usize = 255;
+
+/// Maximum payload carried per fragment
+pub const FRAGMENT_PAYLOAD_MAX: usize = FRAGMENT_MAX - FRAGMENT_HEADER_LEN;
+
+/// Maximum length of a fragmented message, where fragments of `FRAGMENT_MAX` bytes are supported
+pub const LONG_PACKET_MAX: usize = 255 * FRAGMENT_PAYLOAD_MAX;
+
+/// Compute the number of fragments of up to `frag_len` bytes (including header)
+/// required for a message of `len` bytes
+///
+/// `frag_len` must be greater than `FRAGMENT_HEADER_LEN` and no more than `FRAGMENT_MAX`.
+pub fn fragment_count(len: usize, frag_len: usize) -> usize {
+    len.div_ceil(frag_len - FRAGMENT_HEADER_LEN).max(1)
+}
+
+/// Write the fragment at `index` of the provided message, split into fragments of up to
+/// `frag_len` bytes (including header), into `buff`, returning the fragment length
+///
+/// Callers must check the fragment count (see `fragment_count`) fits the header and the index against this.
+pub fn fragment(data: &[u8], index: usize, frag_len: usize, buff: &mut [u8; FRAGMENT_MAX]) -> usize {
+    let payload_max = frag_len - FRAGMENT_HEADER_LEN;
+    let start = index * payload_max;
+    let end = (start + payload_max).min(data.len());
+    let len = end - start;
+
+    buff[0] = index as u8;
+    buff[1] = fragment_count(data.len(), frag_len) as u8;
+    buff[2] = len as u8;
+    buff[FRAGMENT_HEADER_LEN..FRAGMENT_HEADER_LEN + len].copy_from_slice(&data[start..end]);
+
+    FRAGMENT_HEADER_LEN + len
+}
+
+/// Reassembler rebuilds a fragmented message into the provided buffer
+pub struct Reassembler<'a> {
+    buff: &'a mut [u8],
+    next: u8,
+    count: u8,
+    len: usize,
+}
+
+impl <'a> Reassembler<'a> {
+    /// Create a new reassembler writing into `buff`
+    pub fn new(buff: &'a mut [u8]) -> Self {
+        Self { buff, next: 0, count: 0, len: 0 }
+    }
+
+    /// Push a received fragment, returning the message length once all fragments are received
+    ///
+    /// A fragment with index 0 (re)starts reassembly, and any bytes following the payload
+    /// length in the header (ie. fixed length padding) are discarded. Returns `ValidationError::InvalidSequence`
+    /// for fragments received out of order, or `ValidationError::InvalidLength` for malformed fragments
+    /// or where the message exceeds the provided buffer.
+    pub fn push(&mut self, frag: &[u8]) -> Result<Option<usize>, ValidationError> {
+        if frag.len() < FRAGMENT_HEADER_LEN {
+            return Err(ValidationError::InvalidLength)
+        }
+
+        let (index, count, len) = (frag[0], frag[1], frag[2] as usize);
+
+        if len > frag.len() - FRAGMENT_HEADER_LEN {
+            warn!("Fragment payload length {} exceeds fragment ({} bytes)", len, frag.len());
+            return Err(ValidationError::InvalidLength)
+        }
+
+        if index == 0 {
+            self.next = 0;
+            self.count = count;
+            self.len = 0;
+        }
+
+        if count == 0 || index != self.next || count != self.count {
+            warn!("Unexpected fragment {} of {} (expected: {} of {})", index, count, self.next, self.count);
+            return Err(ValidationError::InvalidSequence)
+        }
+
+        let payload = &frag[FRAGMENT_HEADER_LEN..FRAGMENT_HEADER_LEN + len];
+        if self.len + payload.len() > self.buff.len() {
+            warn!("Reassembled message exceeds buffer length ({})", self.buff.len());
+            return Err(ValidationError::InvalidLength)
+        }
+
+        self.buff[self.len..self.len + payload.len()].copy_from_slice(payload);
+        self.len += payload.len();
+        self.next += 1;
+
+        match self.next == self.count {
+            true => Ok(Some(self.len)),
+            false => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Radio-agnostic packet framing, prefixing payloads with a length byte and appending a CRC16
+//!
+//! This is independent of the device packet engine (and CRC), providing consistent framing
+//! and integrity checks across modems when using variable length (or fixed length) packets.
+
+
+use log::warn;
+
+use crate::ValidationError;
+
+/// Framing overhead in bytes (length prefix and CRC16)
+pub const FRAME_OVERHEAD: usize = 3;
+
+/// Maximum framed packet length
+pub const FRAME_MAX: usize = 255;
+
+/// Maximum payload length for a framed packet
+pub const FRAME_PAYLOAD_MAX: usize = FRAME_MAX - FRAME_OVERHEAD;
+
+/// Compute the CRC16 (CCITT-FALSE, polynomial 0x1021, initial value 0xFFFF) of the provided data
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+
+    for b in data {
+        crc ^= (*b as u16) << 8;
+
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+
+    crc
+}
+
+/// Framer builds length-prefixed, CRC protected packets for transmission
+pub struct Framer {
+    buff: [u8; FRAME_MAX],
+}
+
+impl Default for Framer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Framer {
+    /// Create a new framer
+    pub fn new() -> Self {
+        Self { buff: [0u8; FRAME_MAX] }
+    }
+
+    /// Frame the provided payload, returning the framed packet (for `start_transmit`)
+    ///
+    /// Returns `ValidationError::InvalidLength` if the payload exceeds `FRAME_PAYLOAD_MAX`.
+    pub fn frame(&mut self, payload: &[u8]) -> Result<&[u8], ValidationError> {
+        if payload.len() > FRAME_PAYLOAD_MAX {
+            warn!("Payload too long for framing (actual: {}, max: {})", payload.len(), FRAME_PAYLOAD_MAX);
+            return Err(ValidationError::InvalidLength)
+        }
+
+        let len = payload.len() + 1;
+
+        self.buff[0] = payload.len() as u8;
+        self.buff[1..len].copy_from_slice(payload);
+
+        let crc = crc16(&self.buff[..len]);
+        self.buff[len] = (crc >> 8) as u8;
+        self.buff[len + 1] = crc as u8;
+
+        Ok(&self.buff[..len + 2])
+    }
+}
+
+/// Validate and strip framing from a received packet, returning the contained payload
+///
+/// Trailing data beyond the framed length (eg. fixed length padding) is ignored.
+/// Returns `ValidationError::InvalidLength` if the packet is shorter than the framed length,
+/// or `ValidationError::InvalidCrc` if the CRC does not match.
+pub fn deframe(data: &[u8]) -> Result<&[u8], ValidationError> {
+    let len = match data.first() {
+        Some(l) => *l as usize + 1,
+        None => return Err(ValidationError::InvalidLength),
+    };
+
+    if data.len() < len + 2 {
+        warn!("Framed packet truncated (actual: {}, expected: {})", data.len(), len + 2);
+        return Err(ValidationError::InvalidLength)
+    }
+
+    let crc = (data[len] as u16) << 8 | data[len + 1] as u16;
+    if crc16(&data[..len]) != crc {
+        return Err(ValidationError::InvalidCrc)
+    }
+
+    Ok(&data[1..len])
+}
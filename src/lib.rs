@@ -7,9 +7,6 @@ use core::marker::PhantomData;
 use core::convert::TryFrom;
 use core::fmt::Debug;
 
-
-extern crate libc;
-
 #[cfg(any(test, feature = "util"))]
 #[macro_use]
 extern crate std;
@@ -23,7 +20,7 @@ use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal::spi::{Mode as SpiMode, Phase, Polarity};
 use embedded_hal::blocking::spi::{Transfer, Write, Transactional};
 
-use driver_pal::{Error as WrapError, wrapper::Wrapper as SpiWrapper};
+use driver_pal::{Error as WrapError, PinState, wrapper::Wrapper as SpiWrapper};
 
 pub use radio::{State as _, Interrupts as _, Channel as _};
 
@@ -34,9 +31,21 @@ pub mod base;
 pub mod device;
 pub use device::{State, Config};
 use device::*;
+use device::common::{SyncWordRxMatch, WhiteningModes};
+use device::gfsk::GfskSyncMatch;
+use device::lora::{LoRaCadSymbols, CadExitMode, CadEvent};
 
 pub mod prelude;
 
+pub mod ll;
+
+#[cfg(feature = "framing")]
+pub mod framing;
+
+pub mod fragment;
+
+pub mod pingpong;
+
 /// Sx128x Spi operating mode
 pub const SPI_MODE: SpiMode = SpiMode {
     polarity: Polarity::IdleLow,
@@ -44,11 +53,54 @@ pub const SPI_MODE: SpiMode = SpiMode {
 };
 
 /// Sx128x device object
-pub struct Sx128x<Base, CommsError, PinError, DelayError> {
+pub struct Sx128x<Base, CommsError, PinError, DelayError>
+where
+    Base: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
     config: Config,
     packet_type: PacketType,
     hal: Base,
 
+    /// Sleep configuration used when the device was last put to sleep
+    sleep_config: Option<SleepConfig>,
+
+    /// Queue of received packets for non-clearing receive
+    rx_queue: RxQueue,
+
+    /// TX and RX buffer regions, see `Sx128x::set_buffer_layout`
+    buffer_layout: Option<BufferLayout>,
+
+    /// Last configured frequency, for recalibration on large frequency changes
+    last_freq: Option<u32>,
+
+    /// Raw RSSI threshold for carrier detection (GFSK and FLRC modes)
+    rssi_threshold: Option<u8>,
+
+    /// Remaining RX settle time since entering receive mode, see `Config::rx_settle_us`
+    rx_settle_us: Option<u32>,
+
+    /// Primed `SetRx` timeout for CAD with `CadExitMode::Rx`, see `Sx128x::start_cad`
+    cad_rx: Option<[u8; 3]>,
+
+    /// Preamble detected since receive mode was entered, latched as polling clears IRQs
+    /// so a later timeout is not mistaken for a preamble timeout
+    preamble_detected: bool,
+
+    /// RX buffer base for which RX modem and buffer configuration was last applied,
+    /// cleared by any subsequent modem or buffer configuration (see `Config::reconfigure_on_rx`)
+    rx_configured: Option<u8>,
+
+    /// Activity callback, see `Sx128x::set_activity_hook`
+    activity_hook: Option<fn(Activity)>,
+
+    /// State change callback, see `Sx128x::set_state_change_hook`
+    on_state_change: Option<fn(State, State)>,
+    /// Last state requested by the driver
+    last_state: State,
+
     _ce: PhantomData<CommsError>, 
     _pe: PhantomData<PinError>,
     _de: PhantomData<DelayError>,
@@ -57,8 +109,16 @@ pub struct Sx128x<Base, CommsError, PinError, DelayError> {
 pub const FREQ_MIN: u32 = 2_400_000_000;
 pub const FREQ_MAX: u32 = 2_500_000_000;
 
+/// Maximum PLL step count representable in `SetRfFrequency`
+pub const FREQ_STEPS_MAX: u32 = 0x00FF_FFFF;
+
 pub const NUM_RETRIES: usize = 3;
 
+/// Number of BUSY samples taken by `check_busy_line`
+pub const BUSY_CHECK_SAMPLES: u32 = 10;
+/// Interval between BUSY samples taken by `check_busy_line`
+pub const BUSY_CHECK_INTERVAL_US: u32 = 100;
+
 /// Sx128x error type
 #[derive(Debug, Clone, PartialEq, Fail)]
 pub enum Error<
@@ -95,10 +155,22 @@ pub enum Error<
     /// CRC error on received message
     InvalidCrc,
 
+    #[fail(display="invalid message header")]
+    /// Header error on received message (LoRa mode)
+    InvalidHeader,
+
+    #[fail(display="preamble timeout")]
+    /// Receive timeout with no preamble detected
+    PreambleTimeout,
+
     #[fail(display="invalid message length")]
     /// Invalid message length
     InvalidLength,
     
+    #[fail(display="invalid fragment sequence")]
+    /// Long packet fragment received out of sequence
+    InvalidSequence,
+
     #[fail(display="invalid sync word")]
     /// TODO
     InvalidSync,
@@ -130,6 +202,51 @@ pub enum Error<
     #[fail(display="device communication failed")]
     /// No SPI communication detected
     NoComms,
+
+    #[fail(display="operation not supported")]
+    /// Operation not supported by the `Hal` implementation
+    Unsupported,
+}
+
+/// Error type for software-only checks (configuration validation, packet framing, and
+/// fragment reassembly), converted into the matching `Error` variant by the driver
+#[derive(Debug, Clone, Copy, PartialEq, Fail)]
+pub enum ValidationError {
+    #[fail(display="invalid configuration")]
+    /// Invalid configuration option provided
+    InvalidConfiguration,
+
+    #[fail(display="invalid frequency or frequency out of range")]
+    /// Frequency out of range
+    InvalidFrequency,
+
+    #[fail(display="invalid message length")]
+    /// Invalid message length
+    InvalidLength,
+
+    #[fail(display="invalid message CRC")]
+    /// CRC error on received message
+    InvalidCrc,
+
+    #[fail(display="invalid fragment sequence")]
+    /// Long packet fragment received out of sequence
+    InvalidSequence,
+}
+
+impl <CommsError, PinError, DelayError> From<ValidationError> for Error<CommsError, PinError, DelayError> where
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    fn from(e: ValidationError) -> Self {
+        match e {
+            ValidationError::InvalidConfiguration => Error::InvalidConfiguration,
+            ValidationError::InvalidFrequency => Error::InvalidFrequency,
+            ValidationError::InvalidLength => Error::InvalidLength,
+            ValidationError::InvalidCrc => Error::InvalidCrc,
+            ValidationError::InvalidSequence => Error::InvalidSequence,
+        }
+    }
 }
 
 impl <CommsError, PinError, DelayError> From<WrapError<CommsError, PinError, DelayError>> for Error<CommsError, PinError, DelayError> where
@@ -149,6 +266,9 @@ impl <CommsError, PinError, DelayError> From<WrapError<CommsError, PinError, Del
 
 pub type Sx128xSpi<Spi, SpiError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError> = Sx128x<SpiWrapper<Spi, SpiError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError>, SpiError, PinError, DelayError>;
 
+/// Sx128x over SPI with configurable BUSY and DIO polarity, see `Sx128x::spi_polarity`
+pub type Sx128xSpiPolarity<Spi, SpiError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError> = Sx128x<base::Polarity<SpiWrapper<Spi, SpiError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError>>, SpiError, PinError, DelayError>;
+
 
 
 impl<Spi, CommsError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError> Sx128x<SpiWrapper<Spi, CommsError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError>, CommsError, PinError, DelayError>
@@ -164,9 +284,41 @@ where
     DelayError: Debug + Sync + Send + 'static,
 {
     /// Create an Sx128x with the provided `Spi` implementation and pins
+    ///
+    /// BUSY and DIO are active high, configurations setting `Config::busy_active_low` or
+    /// `Config::dio_active_low` are rejected, see `Sx128x::spi_polarity`.
     pub fn spi(spi: Spi, cs: CsPin, busy: BusyPin, ready: ReadyPin, sdn: SdnPin, delay: Delay, config: &Config) -> Result<Self, Error<CommsError, PinError, DelayError>> {
+        if config.busy_active_low || config.dio_active_low {
+            warn!("Active low BUSY or DIO requires `Sx128x::spi_polarity`");
+            return Err(Error::InvalidConfiguration)
+        }
+
+        // Create SpiWrapper over spi/cs/busy
+        let hal = SpiWrapper::new(spi, cs, sdn, busy, ready, delay);
+        // Create instance with new hal
+        Self::new(hal, config)
+    }
+}
+
+impl<Spi, CommsError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError> Sx128x<base::Polarity<SpiWrapper<Spi, CommsError, CsPin, BusyPin, ReadyPin, SdnPin, PinError, Delay, DelayError>>, CommsError, PinError, DelayError>
+where
+    Spi: Transfer<u8, Error = CommsError> + Write<u8, Error = CommsError> + Transactional<u8, Error = CommsError>,
+    CsPin: OutputPin<Error = PinError>,
+    BusyPin: InputPin<Error = PinError>,
+    ReadyPin: InputPin<Error = PinError>,
+    SdnPin: OutputPin<Error = PinError>,
+    Delay: delay::DelayMs<u32, Error=DelayError> + delay::DelayUs<u32, Error=DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    /// Create an Sx128x with the provided `Spi` implementation and pins
+    ///
+    /// BUSY and DIO polarity are set from `Config::busy_active_low` and `Config::dio_active_low`.
+    pub fn spi_polarity(spi: Spi, cs: CsPin, busy: BusyPin, ready: ReadyPin, sdn: SdnPin, delay: Delay, config: &Config) -> Result<Self, Error<CommsError, PinError, DelayError>> {
         // Create SpiWrapper over spi/cs/busy
         let hal = SpiWrapper::new(spi, cs, sdn, busy, ready, delay);
+        let hal = base::Polarity::new(hal, config.busy_active_low, config.dio_active_low);
         // Create instance with new hal
         Self::new(hal, config)
     }
@@ -183,30 +335,61 @@ where
     /// Create a new Sx128x instance over a generic Hal implementation
     pub fn new(hal: Hal, config: &Config) -> Result<Self, Error<CommsError, PinError, DelayError>> {
 
+        // Validate configuration prior to bus access
+        config.validate()?;
+
         let mut sx128x = Self::build(hal);
 
-        debug!("Resetting device");
+        let mut attempt = 0;
 
-        // Reset IC
-        sx128x.hal.reset()?;
+        let firmware_version = loop {
+            debug!("Resetting device (attempt {})", attempt);
 
-        debug!("Checking firmware version");
+            // Reset IC, extending the settle time on each retry
+            let settle_ms = config.reset_settle_ms * (attempt as u32 + 1);
+            sx128x.hal.reset_timed(config.reset_assert_ms, settle_ms)?;
 
-        // Check communication with the radio
-        let firmware_version = sx128x.firmware_version()?;
-        
-        if firmware_version == 0xFFFF || firmware_version == 0x0000 {
-            return Err(Error::NoComms)
-        } else if firmware_version != 0xA9B5 {
-            warn!("Invalid firmware version! expected: 0x{:x} actual: 0x{:x}", 0xA9B5, firmware_version);
-        }
+            debug!("Checking firmware version");
+
+            // Check communication with the radio
+            let firmware_version = sx128x.firmware_version()?;
+
+            if firmware_version != 0xFFFF && firmware_version != 0x0000 {
+                break firmware_version;
+            } else if attempt >= config.reset_retries {
+                return Err(Error::NoComms)
+            }
+
+            warn!("No response from device (firmware version 0x{:x}), retrying reset", firmware_version);
+            attempt += 1;
+        };
 
-        if firmware_version != 0xA9B5 && !config.skip_version_check {
-            return Err(Error::InvalidDevice(firmware_version));
+        if !config.accepted_firmware_versions.contains(&firmware_version) {
+            #[allow(deprecated)]
+            let policy = match config.skip_version_check {
+                true => VersionMismatchPolicy::Ignore,
+                false => config.version_mismatch_policy,
+            };
+
+            match policy {
+                VersionMismatchPolicy::Error => {
+                    error!("Invalid firmware version! expected one of: {:x?} actual: 0x{:x}", config.accepted_firmware_versions, firmware_version);
+                    return Err(Error::InvalidDevice(firmware_version));
+                },
+                VersionMismatchPolicy::Warn => {
+                    error!("Invalid firmware version! expected one of: {:x?} actual: 0x{:x}, continuing", config.accepted_firmware_versions, firmware_version);
+                },
+                VersionMismatchPolicy::Ignore => {
+                    debug!("Ignoring firmware version mismatch (0x{:x})", firmware_version);
+                },
+            }
         }
 
-        // TODO: do we need to calibrate things here?
-        //sx128x.calibrate(CalibrationParams::default())?;
+        // Calibrate device
+        if !config.skip_calibration {
+            debug!("Calibrating device");
+            sx128x.calibrate(CalibrationParams::all())?;
+        }
 
         debug!("Configuring device");
 
@@ -214,518 +397,606 @@ where
         sx128x.configure(config)?;
 
         // Ensure state is idle
-        sx128x.set_state(State::StandbyRc)?;
+        sx128x.set_state(config.initial_standby)?;
 
         Ok(sx128x)
     }
 
+    /// Hard reset the device by pulsing NRESET, clearing all device configuration
+    ///
+    /// This should be used where the device is unresponsive (eg. `Error::BusyTimeout` or
+    /// `Error::NoComms`), and must be followed by `configure` prior to use. For recovering
+    /// from transient faults with a responsive device see `soft_reset`.
     pub fn reset(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
         debug!("Resetting device");
 
-        self.hal.reset()?;
+        self.hal.reset_timed(self.config.reset_assert_ms, self.config.reset_settle_ms)?;
+
+        // Device returns to STDBY_RC with the default packet type, buffer bases, and frequency
+        self.sleep_config = None;
+        self.packet_type = PacketType::None;
+        self.rx_configured = None;
+        self.config.buffer_base = None;
+        self.buffer_layout = None;
+        self.last_freq = None;
+        self.state_changed(State::StandbyRc);
+
+        Ok(())
+    }
+
+    /// Put the device to sleep, retaining the memories selected in `retain`
+    ///
+    /// With `SleepConfig::RETAIN_DATA_RAM` set the device configuration is
+    /// preserved, allowing `wakeup` to resume without reconfiguring the device.
+    pub fn sleep(&mut self, retain: SleepConfig) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        debug!("Entering sleep (retain: {:?})", retain);
+
+        self.hal.sleep(retain.bits())?;
+        self.sleep_config = Some(retain);
+        self.state_changed(State::Sleep);
+
+        Ok(())
+    }
+
+    /// Wake the device from sleep
+    ///
+    /// If the device was put to sleep without data RAM retention the
+    /// configuration is lost, and is re-applied from the driver's copy.
+    pub fn wakeup(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        debug!("Waking device");
+
+        self.hal.wakeup()?;
+        self.state_changed(State::StandbyRc);
+
+        match self.sleep_config.take() {
+            Some(c) if c.contains(SleepConfig::RETAIN_DATA_RAM) => {
+                trace!("Configuration retained, skipping reconfiguration");
+            },
+            _ => {
+                // Device resets to default packet type on a cold wakeup
+                self.packet_type = PacketType::None;
+
+                let config = self.config.clone();
+                self.configure(&config)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Put the device to sleep then wake it, verifying the device entered sleep
+    ///
+    /// BUSY is held high for the duration of sleep, so this samples BUSY prior to waking
+    /// the device, returning `Error::InvalidState` where BUSY is low (ie. the device never
+    /// slept). This is useful for detecting NSS / BUSY wiring faults.
+    pub fn sleep_and_verify(&mut self, cfg: SleepConfig) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let previous = self.last_state;
+
+        self.sleep(cfg)?;
+
+        if self.hal.get_busy()? == PinState::Low {
+            error!("Device did not enter sleep (BUSY low)");
+
+            // Device remains awake so no wakeup (or reconfiguration) is required
+            self.sleep_config = None;
+            self.state_changed(previous);
+
+            return Err(Error::InvalidState(State::Sleep, previous))
+        }
+
+        self.wakeup()
+    }
+
     pub(crate) fn build(hal: Hal) -> Self {
         Sx128x { 
             config: Config::default(),
             packet_type: PacketType::None,
             hal,
+            sleep_config: None,
+            rx_queue: RxQueue::default(),
+            buffer_layout: None,
+            last_freq: None,
+            rssi_threshold: None,
+            rx_settle_us: None,
+            cad_rx: None,
+            preamble_detected: false,
+            rx_configured: None,
+            activity_hook: None,
+            on_state_change: None,
+            last_state: State::StandbyRc,
             _ce: PhantomData,
             _pe: PhantomData,
             _de: PhantomData,
         }
     }
 
+    /// Apply a new configuration to the device
+    ///
+    /// If any write fails the driver configuration is restored to its prior value,
+    /// so the cached configuration is never left partially updated.
+    ///
+    /// BUSY and DIO polarity (`Config::busy_active_low`, `Config::dio_active_low`) are set
+    /// at construction and are not changed here.
     pub fn configure(&mut self, config: &Config) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        // Switch to standby mode
-        self.set_state(State::StandbyRc)?;
+        // Validate configuration
+        config.validate()?;
+
+        // Snapshot existing configuration for rollback on failure
+        let previous = self.config.clone();
 
-        // Check configs match
-        match (&config.modem, &config.channel) {
-            (Modem::LoRa(_), Channel::LoRa(_)) => (),
-            (Modem::Flrc(_), Channel::Flrc(_)) => (),
-            (Modem::Gfsk(_), Channel::Gfsk(_)) => (),
-            _ => return Err(Error::InvalidConfiguration)
+        if let Err(e) = self.write_config(config) {
+            warn!("Configuration failed ({:?}), restoring previous configuration", e);
+            self.config = previous;
+            return Err(e)
         }
 
+        Ok(())
+    }
+
+    /// Write configuration to the device
+    ///
+    /// The driver configuration is adopted prior to writing so values derived from it
+    /// (eg. channel frequency steps from the crystal configuration) use the new configuration,
+    /// `configure` restores the prior configuration where any write fails.
+    fn write_config(&mut self, config: &Config) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.config = config.clone();
+
+        // Switch to standby mode
+        self.set_state(config.initial_standby)?;
+
         // Update regulator mode
-        self.set_regulator_mode(config.regulator_mode)?;
-        self.config.regulator_mode = config.regulator_mode;
+        self.write_regulator_mode(config.regulator_mode, config.pa_config.power)?;
 
         // Update modem and channel configuration
-        self.set_channel(&config.channel)?;
-        self.config.channel = config.channel.clone();
+        self.write_channel(&config.channel)?;
+        self.write_modem(&config.modem)?;
 
-        self.configure_modem(&config.modem)?;
-        self.config.modem = config.modem.clone();
-
-        // Update power amplifier configuration
+        // Update power amplifier configuration, retaining the applied (clamped) power
         self.set_power_ramp(config.pa_config.power, config.pa_config.ramp_time)?;
-        self.config.pa_config = config.pa_config.clone();
+
+        // Update receiver gain mode
+        self.set_rx_boosted(config.rx_boosted)?;
+
+        // Update buffer base addresses, bases are otherwise reset on each transmit or receive
+        match config.buffer_base {
+            Some((tx, rx)) => self.set_buffer_base(tx, rx)?,
+            None => self.buffer_layout = None,
+        }
 
         Ok(())
     }
 
-    pub fn firmware_version(&mut self) -> Result<u16, Error<CommsError, PinError, DelayError>> {
-        let mut d = [0u8; 2];
-
-        self.hal.read_regs(Registers::LrFirmwareVersionMsb as u16, &mut d)?;
+    /// Fetch the current driver configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
 
-        Ok((d[0] as u16) << 8 | (d[1] as u16))
+    /// Fetch the currently configured packet type
+    pub fn packet_type(&self) -> PacketType {
+        self.packet_type
     }
 
-    pub fn set_frequency(&mut self, f: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        let c = self.config.freq_to_steps(f as f32) as u32;
+    /// Fetch the maximum transmit payload length for the current modem configuration
+    ///
+    /// This is the configured payload length for fixed length (or LoRa implicit header)
+    /// packets, `FLRC_PAYLOAD_MAX` for variable length FLRC, the PDU limit plus 2-byte
+    /// header for BLE, and 255 bytes otherwise, limited to the TX region length where a
+    /// buffer layout is set (see `set_buffer_layout`).
+    pub fn max_payload_len(&self) -> u8 {
+        use device::ble::BleConnectionStates::*;
+
+        let max = match (self.fixed_payload_len(), &self.config.modem) {
+            (Some(len), _) => len,
+            (None, Modem::Flrc(_)) => device::flrc::FLRC_PAYLOAD_MAX,
+            (None, Modem::Ble(c)) => match c.connection_state {
+                BLE_PAYLOAD_LENGTH_MAX_31_BYTES => 31 + 2,
+                BLE_PAYLOAD_LENGTH_MAX_37_BYTES | BLE_TX_TEST_MODE => 37 + 2,
+                BLE_PAYLOAD_LENGTH_MAX_255_BYTES => 255,
+            },
+            _ => 255,
+        };
 
-        trace!("Setting frequency ({:?} MHz, {} index)", f / 1000 / 1000, c);
+        match &self.buffer_layout {
+            Some(l) => max.min(l.tx_len),
+            None => max,
+        }
+    }
 
-        let data: [u8; 3] = [
-            (c >> 16) as u8,
-            (c >> 8) as u8,
-            (c >> 0) as u8,
-        ];
+    /// Fetch the payload length for fixed length (or LoRa implicit header) modem configurations
+    fn fixed_payload_len(&self) -> Option<u8> {
+        use device::common::GfskFlrcPacketLength::*;
+        use device::lora::LoRaHeader;
 
-        self.hal.write_cmd(Commands::SetRfFrequency as u8, &data)
+        match &self.config.modem {
+            Modem::LoRa(c) | Modem::Ranging(c) if c.header_type == LoRaHeader::Implicit => Some(c.payload_length),
+            Modem::Gfsk(c) if c.header_type == Fixed => Some(c.payload_length),
+            Modem::Flrc(c) if c.header_type == Fixed => Some(c.payload_length.min(device::flrc::FLRC_PAYLOAD_MAX)),
+            _ => None,
+        }
     }
 
-    pub (crate) fn set_power_ramp(&mut self, power: i8, ramp: RampTime) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        
-        if power > 13 || power < -18 {
-            warn!("TX power out of range (-18 < p < 13)");
+    /// Poll for the mean channel RSSI over `samples` instantaneous measurements,
+    /// taken `interval_us` microseconds apart
+    ///
+    /// As with `radio::Rssi::poll_rssi` this returns `Error::InvalidState` if the device is not in receive mode.
+    pub fn poll_rssi_averaged(&mut self, samples: u8, interval_us: u32) -> Result<i16, Error<CommsError, PinError, DelayError>> {
+        self.poll_rssi_stats(samples, interval_us).map(|s| s.mean )
+    }
+
+    /// Poll for channel RSSI statistics (mean, min, and max) over `samples` instantaneous
+    /// measurements, taken `interval_us` microseconds apart
+    pub fn poll_rssi_stats(&mut self, samples: u8, interval_us: u32) -> Result<RssiStats, Error<CommsError, PinError, DelayError>> {
+        if samples == 0 {
+            return Err(Error::InvalidConfiguration)
         }
 
-        // Limit to -18 to +13 dBm
-        let power = core::cmp::max(power, -18);
-        let power = core::cmp::min(power, 13);
-        let power_reg = (power + 18) as u8;
+        let state = self.get_state()?;
+        if state != State::Rx {
+            warn!("RSSI polled outside of receive mode (state: {:?})", state);
+            return Err(Error::InvalidState(State::Rx, state));
+        }
 
-        trace!("Setting TX power to {} dBm {:?} ramp ({}, {})", power, ramp, power_reg, ramp as u8);
-        self.config.pa_config.power = power;
-        self.config.pa_config.ramp_time = ramp;
+        let (mut sum, mut min, mut max) = (0i32, i16::MAX, i16::MIN);
 
-        self.hal.write_cmd(Commands::SetTxParams as u8, &[ power_reg, ramp as u8 ])
-    }
+        for i in 0..samples {
+            if i != 0 {
+                self.hal.try_delay_us(interval_us).map_err(Error::Delay)?;
+            }
 
-    /// Set IRQ mask
-    pub fn set_irq_mask(&mut self, irq: Irq) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Setting IRQ mask: {:?}", irq);
+            let rssi = self.poll_rssi_unchecked()?;
 
-        let raw = irq.bits();
-        self.hal.write_cmd(Commands::SetDioIrqParams as u8, &[ (raw >> 8) as u8, (raw & 0xff) as u8])
+            sum += rssi as i32;
+            min = min.min(rssi);
+            max = max.max(rssi);
+        }
+
+        let stats = RssiStats{ mean: (sum / samples as i32) as i16, min, max };
+
+        trace!("RSSI stats over {} samples: {:?}", samples, stats);
+
+        Ok(stats)
     }
 
-    /// Set the IRQ and DIO masks
-    pub fn set_irq_dio_mask(&mut self, irq: Irq, dio1: DioMask, dio2: DioMask, dio3: DioMask) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Setting IRQ mask: {:?} DIOs: {:?}, {:?}, {:?}", irq, dio1, dio2, dio3);
+    /// Poll for receive events, returning the highest priority pending event and clearing
+    /// only the corresponding interrupt (for `RxEvent::CrcError` the associated RX done
+    /// interrupt is also cleared)
+    ///
+    /// Events are prioritised as `CrcError`, `Done`, `HeaderError`, `PreambleTimeout`, `Timeout`, `HeaderValid`,
+    /// then `PreambleDetected`, allowing early events to be used (eg. to reserve the medium) prior to packet completion.
+    pub fn poll_receive(&mut self) -> Result<Option<RxEvent>, Error<CommsError, PinError, DelayError>> {
+        let irq = self.get_interrupts(false)?;
+        self.preamble_detected |= irq.contains(Irq::PREAMBLE_DETECTED);
 
-        let raw_irq = irq.bits();
-        let raw_dio1 = dio1.bits();
-        let raw_dio2 = dio2.bits();
-        let raw_dio3 = dio3.bits();
+        let (event, mask) = if irq.contains(Irq::CRC_ERROR) {
+            (RxEvent::CrcError, Irq::CRC_ERROR | (irq & Irq::RX_DONE))
+        } else if irq.contains(Irq::RX_DONE) {
+            self.rx_queue.pending = true;
+            (RxEvent::Done, Irq::RX_DONE)
+        } else if irq.contains(Irq::HEADER_ERROR) {
+            (RxEvent::HeaderError, Irq::HEADER_ERROR)
+        } else if irq.contains(Irq::RX_TX_TIMEOUT) && !self.preamble_detected {
+            (RxEvent::PreambleTimeout, Irq::RX_TX_TIMEOUT)
+        } else if irq.contains(Irq::RX_TX_TIMEOUT) {
+            (RxEvent::Timeout, Irq::RX_TX_TIMEOUT | Irq::PREAMBLE_DETECTED)
+        } else if irq.contains(Irq::HEADER_VALID) {
+            (RxEvent::HeaderValid, Irq::HEADER_VALID)
+        } else if irq.contains(Irq::PREAMBLE_DETECTED) {
+            (RxEvent::PreambleDetected, Irq::PREAMBLE_DETECTED)
+        } else {
+            return Ok(None)
+        };
 
-        let data = [ 
-            (raw_irq >> 8) as u8, 
-            (raw_irq & 0xff) as u8,
+        debug!("RX event: {:?}", event);
 
-            (raw_dio1 >> 8) as u8, 
-            (raw_dio1 & 0xff) as u8,
-            (raw_dio2 >> 8) as u8, 
-            (raw_dio2 & 0xff) as u8,
-            (raw_dio3 >> 8) as u8, 
-            (raw_dio3 & 0xff) as u8,
-        ];
+        self.clear_irq(mask)?;
 
-        self.hal.write_cmd(Commands::SetDioIrqParams as u8, &data)
+        Ok(Some(event))
     }
 
-    pub(crate) fn configure_modem(&mut self, config: &Modem) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        use Modem::*;
+    /// Check whether a packet is currently being received (ie. a preamble, sync word or
+    /// header has been detected but the packet is not yet complete), so transmission can
+    /// be deferred rather than clobbering an in-progress reception
+    ///
+    /// This reads the IRQ status without clearing it.
+    pub fn is_receiving(&mut self) -> Result<bool, Error<CommsError, PinError, DelayError>> {
+        let irq = self.get_interrupts(false)?;
 
-        debug!("Setting modem config: {:?}", config);
+        let started = irq.intersects(Irq::PREAMBLE_DETECTED | Irq::SYNCWORD_VALID | Irq::HEADER_VALID);
 
-        // First update packet type (if required)
-        let packet_type = PacketType::from(config);
-        if self.packet_type != packet_type {
-            trace!("Setting packet type: {:?}", packet_type);
-            self.hal.write_cmd(Commands::SetPacketType as u8, &[ packet_type.clone() as u8 ] )?;
-            self.packet_type = packet_type;
+        Ok(started && !irq.contains(Irq::RX_DONE))
+    }
+
+    /// Check for receive completion using the DIO1 interrupt line
+    ///
+    /// This reads the DIO1 (ready) pin first and returns `Ok(false)` without any SPI traffic
+    /// when it is not asserted, only falling back to `radio::Receive::check_receive` once an
+    /// interrupt is signalled. DIO1 is mapped to all receive IRQs by `start_receive`.
+    pub fn check_receive_irq(&mut self) -> Result<bool, Error<CommsError, PinError, DelayError>> {
+        use radio::Receive;
+
+        if self.hal.get_dio()? == PinState::Low {
+            return Ok(false)
         }
 
-        let data = match config {
-            Gfsk(c) => [c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.whitening as u8],
-            LoRa(c) | Ranging(c) => [c.preamble_length as u8, c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.invert_iq as u8, 0u8, 0u8],
-            Flrc(c) => [c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.whitening as u8],
-            Ble(c) => [c.connection_state as u8, c.crc_field as u8, c.packet_type as u8, c.whitening as u8, 0u8, 0u8, 0u8],
-            None => [0u8; 7],
-        };
+        self.check_receive(false)
+    }
 
-        self.hal.write_cmd(Commands::SetPacketParams as u8, &data)?;
+    /// Abort any in-progress transmit or receive operation, returning the device to STDBY_RC
+    /// with all pending interrupts cleared
+    ///
+    /// This may be called from any state, waking the device first where asleep. Packets already
+    /// queued by `start_receive_nonclearing` are retained, while any pending (unread) packet is discarded.
+    pub fn abort(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        use radio::State as _;
 
+        debug!("Aborting operation");
 
-        // Apply patches
-        match config {
-            Flrc(c) if c.patch_syncword => {
-                // Apply sync-word patch for FLRC mode
-                self.patch_flrc_syncword()?;
-            },
-            Gfsk(c) if c.patch_preamble => {
-                // Write preamble length for GFSK mode
-                self.hal.write_reg(Registers::GfskBlePreambleLength as u16, c.preamble_length as u8)?;
-            },
-            _ => ()
+        // BUSY is held high while asleep, so the device must be woken before issuing commands
+        if self.sleep_config.is_some() {
+            self.wakeup()?;
         }
 
-        Ok(())
-    }
+        self.set_state(State::StandbyRc)?;
+        self.clear_irq(Irq::all())?;
 
-    pub(crate) fn get_rx_buffer_status(&mut self) -> Result<(u8, u8), Error<CommsError, PinError, DelayError>> {
-        use device::lora::LoRaHeader;
+        self.rx_queue.pending = false;
+        self.rx_settle_us = None;
 
-        let mut status = [0u8; 2];
+        Ok(())
+    }
 
-        self.hal.read_cmd(Commands::GetRxBufferStatus as u8, &mut status)?;
+    /// Soft reset the device without toggling NRESET, returning to STDBY_RC, clearing pending
+    /// interrupts and driver receive state, then re-applying the current configuration
+    ///
+    /// This is appropriate for recovering from transient faults (eg. an unexpected state or
+    /// command failure) where the device is still responsive, see `reset` otherwise.
+    /// Note the SX128x has no device error register, command errors are reported in
+    /// the status returned by each command and are logged here.
+    pub fn soft_reset(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        debug!("Soft resetting device");
+
+        self.abort()?;
+
+        let (state, status) = self.get_status()?;
+        if matches!(status, CommandStatus::Timeout | CommandStatus::ProcessingError | CommandStatus::ExecutionFailure) {
+            warn!("Soft reset cleared command status: {:?} (state: {:?})", status, state);
+        }
 
-        let len = match &self.config.modem {
-            Modem::LoRa(c) => {
-                match c.header_type {
-                    LoRaHeader::Implicit => self.hal.read_reg(Registers::LrPayloadLength as u16)?,
-                    LoRaHeader::Explicit => status[0],
-                }
-            },
-            // BLE status[0] does not include 2-byte PDU header
-            Modem::Ble(_) => status[0] + 2,
-            _ => status[0]
-        };
+        self.rx_queue = RxQueue::default();
+        self.cad_rx = None;
+        self.rx_configured = None;
 
-        let rx_buff_ptr = status[1];
+        let config = self.config.clone();
+        self.configure(&config)
+    }
 
-        trace!("RX buffer ptr: {} len: {}", rx_buff_ptr, len);
+    /// Clear only the interrupts specified in `mask`, leaving other pending interrupts untouched
+    pub fn clear_irq(&mut self, mask: Irq) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Clearing IRQ: {:?}", mask);
 
-        Ok((rx_buff_ptr, len))
+        let raw = mask.bits();
+        self.hal.write_cmd(Commands::ClearIrqStatus as u8, &[ (raw >> 8) as u8, (raw & 0xff) as u8 ])
     }
 
-    
-    pub(crate) fn get_packet_info(&mut self, info: &mut PacketInfo) -> Result<(), Error<CommsError, PinError, DelayError>> {
+    /// Wait for any of the interrupts in `mask` to be asserted, polling (without clearing)
+    /// every millisecond for up to `timeout_ms` milliseconds
+    ///
+    /// On success only the matched interrupts are cleared and returned, other pending
+    /// interrupts are left untouched. Returns `Error::Timeout` if no interrupt is matched.
+    pub fn wait_irq(&mut self, mask: Irq, timeout_ms: u32) -> Result<Irq, Error<CommsError, PinError, DelayError>> {
+        let mut elapsed = 0;
 
-        let mut data = [0u8; 5];
-        self.hal.read_cmd(Commands::GetPacketStatus as u8, &mut data)?;
+        loop {
+            let matched = self.get_interrupts(false)? & mask;
 
-        info.packet_status = PacketStatus::from_bits_truncate(data[2]);
-        info.tx_rx_status = TxRxStatus::from_bits_truncate(data[3]);
-        info.sync_addr_status = data[4] & 0b0111;
+            if !matched.is_empty() {
+                trace!("IRQ wait matched: {:?}", matched);
 
-        match self.packet_type {
-            PacketType::Gfsk | PacketType::Flrc | PacketType::Ble => {
-                info.rssi = -(data[1] as i16) / 2;
-                let rssi_avg = -(data[0] as i16) / 2;
-                trace!("Raw RSSI: {}", info.rssi);
-                trace!("Average RSSI: {}", rssi_avg);
-            },
-            PacketType::LoRa | PacketType::Ranging => {
-                info.rssi = -(data[0] as i16) / 2;
-                info.snr = Some(match data[1] < 128 {
-                    true => data[1] as i16 / 4,
-                    false => ( data[1] as i16 - 256 ) / 4
-                });
-            },
-            PacketType::None => unimplemented!(),
-        }
+                self.clear_irq(matched)?;
 
-        debug!("Info: {:?}", info);
+                if matched.contains(Irq::TX_DONE) {
+                    self.activity(Activity::TxDone);
+                }
+                if matched.contains(Irq::RX_DONE) {
+                    self.activity(Activity::RxDone);
+                }
 
-        Ok(())
-    }
+                return Ok(matched)
+            }
 
-    pub fn calibrate(&mut self, c: CalibrationParams) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Calibrate {:?}", c);
-        self.hal.write_cmd(Commands::Calibrate as u8, &[ c.bits() ])
-    }
+            if elapsed >= timeout_ms {
+                debug!("IRQ wait timeout after {} ms (mask: {:?})", elapsed, mask);
+                return Err(Error::Timeout)
+            }
 
-    pub(crate) fn set_regulator_mode(&mut self, r: RegulatorMode) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Set regulator mode {:?}", r);
-        self.hal.write_cmd(Commands::SetRegulatorMode as u8, &[ r as u8 ])
+            self.hal.try_delay_ms(1).map_err(Error::Delay)?;
+            elapsed += 1;
+        }
     }
 
-    // TODO: this could got into a mode config object maybe?
-    #[allow(dead_code)]
-    pub(crate) fn set_auto_tx(&mut self, a: AutoTx) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        let data = match a {
-            AutoTx::Enabled(timeout_us) => {
-                let compensated = timeout_us - AUTO_RX_TX_OFFSET;
-                [(compensated >> 8) as u8, (compensated & 0xff) as u8]
-            },
-            AutoTx::Disabled => [0u8; 2],
-        };
-        self.hal.write_cmd(Commands::SetAutoTx as u8, &data)
-    }
+    /// Enter frequency synthesis (FS) mode, waiting for the PLL to lock on the configured frequency
+    ///
+    /// This allows the radio to be pre-tuned prior to transmitting or receiving, reducing
+    /// the switching time. The device reports FS mode once the PLL is locked, if this does not
+    /// occur within `FS_LOCK_TIMEOUT_MS` (or the command fails) `Error::InvalidState` is returned.
+    pub fn enter_fs(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        debug!("Entering FS mode");
 
-    pub(crate) fn set_buff_base_addr(&mut self, tx: u8, rx: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Set buff base address (tx: {}, rx: {})", tx, rx);
-        self.hal.write_cmd(Commands::SetBufferBaseAddress as u8, &[ tx, rx ])
-    }
+        self.set_state(State::Fs)?;
 
-    /// Set the sychronization mode for a given index (1-3).
-    /// This is 5-bytes for GFSK mode and 4-bytes for FLRC and BLE modes.
-    pub fn set_syncword(&mut self, index: u8, value: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        trace!("Attempting to set sync word index: {} to: {:?}", index, value);
+        let mut elapsed = 0;
 
-        // Check sync words for errata 16.4
-        if self.packet_type == PacketType::Flrc {
-            match &value[0..2] {
-                &[0x8C, 0x32] | &[0x63, 0x0E] => {
-                    error!("Invalid sync word selected (see errata 16.4)");
-                    return Err(Error::InvalidConfiguration);
+        loop {
+            let (state, status) = self.get_status()?;
+
+            match (state, status) {
+                (State::Fs, _) => return Ok(()),
+                (_, CommandStatus::ExecutionFailure) | (_, CommandStatus::ProcessingError) => {
+                    error!("FS mode command failed (state: {:?}, status: {:?})", state, status);
+                    return Err(Error::InvalidState(State::Fs, state))
+                },
+                _ if elapsed >= FS_LOCK_TIMEOUT_MS => {
+                    error!("PLL lock timeout after {} ms (state: {:?})", elapsed, state);
+                    return Err(Error::InvalidState(State::Fs, state))
                 },
                 _ => (),
             }
-        }
-
-        // Calculate sync word base address and expected length
-        let (addr, len) = match (&self.packet_type, index) {
-            (PacketType::Gfsk, 1) => (Registers::LrSyncWordBaseAddress1 as u16, 5),
-            (PacketType::Gfsk, 2) => (Registers::LrSyncWordBaseAddress2 as u16, 5),
-            (PacketType::Gfsk, 3) => (Registers::LrSyncWordBaseAddress3 as u16, 5),
-            (PacketType::Flrc, 1) => (Registers::LrSyncWordBaseAddress1 as u16 + 1, 4),
-            (PacketType::Flrc, 2) => (Registers::LrSyncWordBaseAddress2 as u16 + 1, 4),
-            (PacketType::Flrc, 3) => (Registers::LrSyncWordBaseAddress3 as u16 + 1, 4),
-            (PacketType::Ble, _) => (Registers::LrSyncWordBaseAddress1 as u16 + 1, 4),
-            _ => {
-                warn!("Invalid sync word configuration (mode: {:?} index: {} value: {:?}", self.config.modem, index, value);
-                return Err(Error::InvalidConfiguration)
-            }
-        };
 
-        // Check length is correct
-        if value.len() != len {
-            warn!("Incorrect sync word length for mode: {:?} (actual: {}, expected: {})", self.config.modem, value.len(), len);
-            return Err(Error::InvalidConfiguration)
+            self.hal.try_delay_ms(1).map_err(Error::Delay)?;
+            elapsed += 1;
         }
-
-        // Write sync word
-        self.hal.write_regs(addr, value)?;
-
-        Ok(())
     }
 
-    /// Apply patch for sync-word match errata in FLRC mode
-    fn patch_flrc_syncword(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        // If we're in FLRC mode, patch to force 100% match on syncwords
-        // because otherwise the 4 bit threshold is too low
-        if let PacketType::Flrc = &self.packet_type {
-            let r = self.hal.read_reg(Registers::LrSyncWordTolerance as u16)?;
-            self.hal.write_reg(Registers::LrSyncWordTolerance as u16, r & 0xF0)?;
+    /// Transmit a message longer than a single packet, blocking until all fragments are sent
+    ///
+    /// Messages are split into fragments (see the `fragment` module) of up to `max_payload_len`
+    /// bytes, each transmitted as a separate packet and awaited for up to `Config.timeout_ms`.
+    /// In fixed length modes every fragment is padded to the configured payload length.
+    /// Returns `Error::InvalidLength` for messages requiring more than 255 fragments, or
+    /// `Error::InvalidConfiguration` where packets cannot carry a fragment header and payload.
+    pub fn transmit_long(&mut self, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        use radio::Transmit;
+
+        let frag_len = self.max_payload_len() as usize;
+        if frag_len <= fragment::FRAGMENT_HEADER_LEN {
+            warn!("Packet length ({}) too short for fragments", frag_len);
+            return Err(Error::InvalidConfiguration)
         }
 
-        Ok(())
-    }
-}
-
-
-impl<Hal, CommsError, PinError, DelayError> delay::DelayMs<u32> for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = DelayError;
-
-    fn try_delay_ms(&mut self, t: u32) -> Result<(), DelayError> {
-        self.hal.try_delay_ms(t)
-    }
-}
-
-impl<Hal, CommsError, PinError, DelayError> delay::DelayUs<u32> for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = DelayError;
-
-    fn try_delay_us(&mut self, t: u32) -> Result<(), DelayError> {
-        self.hal.try_delay_us(t)
-    }
-}
+        let count = fragment::fragment_count(data.len(), frag_len);
+        if count > u8::MAX as usize {
+            warn!("Long packet too long (actual: {}, fragments: {})", data.len(), count);
+            return Err(Error::InvalidLength)
+        }
 
-/// `radio::State` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::State for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type State = State;
-    type Error = Error<CommsError, PinError, DelayError>;
+        let fixed = self.fixed_payload_len().is_some();
+        let mut buff = [0u8; fragment::FRAGMENT_MAX];
 
-    /// Fetch device state
-    fn get_state(&mut self) -> Result<Self::State, Self::Error> {
-        let mut d = [0u8; 1];
-        self.hal.read_cmd(Commands::GetStatus as u8, &mut d)?;
+        for i in 0..count {
+            let n = fragment::fragment(data, i, frag_len, &mut buff);
+            // Pad fixed length fragments to the configured payload length
+            let n = match fixed {
+                true => {
+                    buff[n..frag_len].fill(0);
+                    frag_len
+                },
+                false => n,
+            };
 
-        trace!("raw state: 0x{:.2x}", d[0]);
+            trace!("TX fragment {} ({} bytes)", i, n);
 
-        let mode = (d[0] & 0b1110_0000) >> 5;
-        let m = State::try_from(mode).map_err(|_| Error::InvalidResponse(d[0]) )?;
+            self.start_transmit(&buff[..n])?;
 
-        let status = (d[0] & 0b0001_1100) >> 2;
-        let s = CommandStatus::try_from(status).map_err(|_| Error::InvalidResponse(d[0]) )?;
+            let mut elapsed = 0;
+            while !self.check_transmit()? {
+                if elapsed >= self.config.timeout_ms {
+                    return Err(Error::Timeout)
+                }
 
-        trace!("get state: {:?} status: {:?}", m, s);
+                self.hal.try_delay_ms(1).map_err(Error::Delay)?;
+                elapsed += 1;
+            }
+        }
 
-        Ok(m)
+        Ok(())
     }
 
-    /// Set device state
-    fn set_state(&mut self, state: Self::State) -> Result<(), Self::Error> {
-        let command = match state {
-            State::Tx => Commands::SetTx,
-            State::Rx => Commands::SetRx,
-            //State::Cad => Commands::SetCad,
-            State::Fs => Commands::SetFs,
-            State::StandbyRc | State::StandbyXosc => Commands::SetStandby,
-            State::Sleep => Commands::SetSleep,
-        };
-
-        trace!("Setting state {:?} ({:x?})", state, command);
+    /// Receive a message sent with `transmit_long`, blocking until all fragments are received
+    /// or no fragment is received within `timeout_ms`, and returning the message length
+    ///
+    /// Receive is restarted following each fragment, so the transmitter must allow time between
+    /// fragments for this. Returns `Error::InvalidSequence` if fragments are missed.
+    pub fn receive_long(&mut self, data: &mut [u8], timeout_ms: u32) -> Result<usize, Error<CommsError, PinError, DelayError>> {
+        use radio::Receive;
+
+        let mut reassembler = fragment::Reassembler::new(data);
+        let mut buff = [0u8; fragment::FRAGMENT_MAX];
+        let mut info = PacketInfo::default();
+
+        loop {
+            self.start_receive()?;
+
+            let mut elapsed = 0;
+            while !self.check_receive(false)? {
+                if elapsed >= timeout_ms {
+                    return Err(Error::Timeout)
+                }
 
-        self.hal.write_cmd(command as u8, &[ 0u8 ])
-    }
-}
+                self.hal.try_delay_ms(1).map_err(Error::Delay)?;
+                elapsed += 1;
+            }
 
-/// `radio::Busy` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Busy for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = Error<CommsError, PinError, DelayError>;
+            let n = self.get_received(&mut info, &mut buff)?;
 
-    /// Fetch device state
-    fn is_busy(&mut self) -> Result<bool, Self::Error> {
-        
-        let irq = self.get_interrupts(false)?;
+            trace!("RX fragment ({} bytes)", n);
 
-        if irq.contains(Irq::SYNCWORD_VALID) && 
-                !(irq.contains(Irq::RX_DONE) || irq.contains(Irq::CRC_ERROR)){
-            return Ok(true);
+            if let Some(len) = reassembler.push(&buff[..n])? {
+                return Ok(len)
+            }
         }
-
-        Ok(false)
     }
-}
 
-/// `radio::Channel` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Channel for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    /// Channel consists of an operating frequency and packet mode
-    type Channel = Channel;
-    
-    type Error = Error<CommsError, PinError, DelayError>;
-
-    /// Set operating channel
-    fn set_channel(&mut self, ch: &Self::Channel) -> Result<(), Self::Error> {
-        use Channel::*;
+    /// Run LoRa channel activity detection (CAD) over the provided number of symbols,
+    /// returning whether activity was detected
+    ///
+    /// The device is returned to standby on completion. Returns `Error::InvalidConfiguration`
+    /// if not configured for LoRa mode, or `Error::Timeout` if CAD does not complete within
+    /// `Config.timeout_ms`.
+    pub fn channel_activity(&mut self, symbols: LoRaCadSymbols) -> Result<bool, Error<CommsError, PinError, DelayError>> {
+        self.start_cad(symbols, CadExitMode::StandbyRc)?;
 
-        debug!("Setting channel config: {:?}", ch);
-        
-        // Set frequency
-        let freq = ch.frequency();
-        if freq < FREQ_MIN || freq > FREQ_MAX {
-            return Err(Error::InvalidFrequency)
-        }
+        let irqs = Irq::CAD_DONE | Irq::CAD_ACTIVITY_DETECTED;
+        let timeout_ms = self.config.timeout_ms;
+        let irq = self.wait_irq(irqs, timeout_ms)?;
 
-        self.set_frequency(freq)?;
+        let detected = irq.contains(Irq::CAD_ACTIVITY_DETECTED);
 
-        // First update packet type (if required)
-        let packet_type = PacketType::from(ch);
-        if self.packet_type != packet_type {
-            self.hal.write_cmd(Commands::SetPacketType as u8, &[ packet_type.clone() as u8 ] )?;
-            self.packet_type = packet_type;
-        }
-        
-        // Then write modulation configuration
-        let data = match ch {
-            Gfsk(c) => [c.br_bw as u8, c.mi as u8, c.ms as u8],
-            LoRa(c) | Ranging(c) => [c.sf as u8, c.bw as u8, c.cr as u8],
-            Flrc(c) => [c.br_bw as u8, c.cr as u8, c.ms as u8],
-            Ble(c) => [c.br_bw as u8, c.mi as u8, c.ms as u8],
-        };
+        debug!("CAD complete (activity: {})", detected);
 
-        self.hal.write_cmd(Commands::SetModulationParams as u8, &data)
+        Ok(detected)
     }
-}
 
-/// `radio::Power` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Power for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = Error<CommsError, PinError, DelayError>;
-
-    /// Set TX power in dBm
-    fn set_power(&mut self, power: i8) -> Result<(), Error<CommsError, PinError, DelayError>> {
-        let ramp_time = self.config.pa_config.ramp_time;
-        self.set_power_ramp(power, ramp_time)
-    }
-}
+    /// Write data into the device buffer at `offset` without transmitting, for use with
+    /// `transmit_staged` (eg. to pre-load a beacon, or assemble a packet across multiple writes)
+    pub fn stage_tx_data(&mut self, offset: u8, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if offset as usize + data.len() > BUFFER_SIZE {
+            warn!("Staged data (offset: {} length: {}) exceeds {} byte buffer", offset, data.len(), BUFFER_SIZE);
+            return Err(Error::InvalidLength)
+        }
 
-/// `radio::Interrupts` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Interrupts for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Irq = Irq;
-    type Error = Error<CommsError, PinError, DelayError>;
+        self.check_tx_region(offset, data.len())?;
 
-    /// Fetch (and optionally clear) current interrupts
-    fn get_interrupts(&mut self, clear: bool) -> Result<Self::Irq, Self::Error> {
-        let mut data = [0u8; 2];
+        debug!("TX stage data at {}: {:?}", offset, data);
 
-        self.hal.read_cmd(Commands::GetIrqStatus as u8, &mut data)?;
-        let irq = Irq::from_bits((data[0] as u16) << 8 | data[1] as u16).unwrap();
+        self.hal.write_buff_chunked(offset, data, self.config.max_transfer)
+    }
 
-        if clear && !irq.is_empty() {
-            self.hal.write_cmd(Commands::ClearIrqStatus as u8, &data)?;
+    /// Start transmitting `len` bytes already present in the device buffer at `offset`
+    /// (see `stage_tx_data`), without rewriting the buffer contents
+    ///
+    /// Completion is checked as for `radio::Transmit::start_transmit`.
+    pub fn transmit_staged(&mut self, offset: u8, len: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if offset as usize + len as usize > BUFFER_SIZE {
+            warn!("Staged packet (offset: {} length: {}) exceeds {} byte buffer", offset, len, BUFFER_SIZE);
+            return Err(Error::InvalidLength)
         }
 
-        if !irq.is_empty() {
-            trace!("irq: {:?}", irq);
-        }
+        self.check_tx_region(offset, len as usize)?;
 
-        Ok(irq)
+        self.transmit_at(offset, len as usize, None)
     }
-}
 
-/// `radio::Transmit` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Transmit for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = Error<CommsError, PinError, DelayError>;
+    /// Start transmitting a `len` byte packet from the `tx_base` buffer address,
+    /// writing `data` to the buffer first where provided
+    ///
+    /// Returns `Error::InvalidLength` where `len` exceeds `max_payload_len`, without writing to the device.
+    fn transmit_at(&mut self, tx_base: u8, len: usize, data: Option<&[u8]>) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let max = self.max_payload_len();
+        if len > max as usize {
+            warn!("TX payload length {} exceeds maximum {} bytes", len, max);
+            return Err(Error::InvalidLength)
+        }
 
-    /// Start transmitting a packet
-    fn start_transmit(&mut self, data: &[u8]) -> Result<(), Self::Error> {
         debug!("TX start");
 
         // Set state to idle before we write configuration
@@ -734,38 +1005,40 @@ where
         let s = self.get_state()?;
         debug!("TX setup state: {:?}", s);
 
+        // Refuse to overwrite configuration or buffer if standby was not reached
+        self.check_state(s, &CONFIG_STATES)?;
+
         // Set packet mode
         let mut modem_config = self.config.modem.clone();
-        modem_config.set_payload_len(data.len() as u8);
+        modem_config.set_payload_len(len as u8);
 
-        if let Err(e) = self.configure_modem(&modem_config) {
+        if let Err(e) = self.write_modem(&modem_config) {
             let s = self.get_state();
             error!("TX error setting modem (error: {:?}, state: {:?})", e, s);
             return Err(e);
         }
 
-        // Reset buffer addr
-        if let Err(e) = self.set_buff_base_addr(0, 0) {
+        // Reset buffer addr (or restore explicit base addresses)
+        let (_, rx_base) = self.buffer_base();
+        if let Err(e) = self.set_buff_base_addr(tx_base, rx_base) {
             let s = self.get_state();
             error!("TX error setting buffer base addr (error: {:?}, state: {:?})", e, s);
             return Err(e);
         }
 
         // Write data to be sent
-        debug!("TX data: {:?}", data);
-        self.hal.write_buff(0, data)?;
-        
+        if let Some(data) = data {
+            debug!("TX data: {:?}", data);
+            self.hal.write_buff_chunked(tx_base, data, self.config.max_transfer)?;
+        }
+
         // Configure ranging if used
         if PacketType::Ranging == self.packet_type {
             self.hal.write_cmd(Commands::SetRangingRole as u8, &[ RangingRole::Initiator as u8 ])?;
         }
 
         // Setup timout
-        let config = [
-            self.config.rf_timeout.step() as u8,
-            (( self.config.rf_timeout.count() >> 8 ) & 0x00FF ) as u8,
-            (self.config.rf_timeout.count() & 0x00FF ) as u8,
-        ];
+        let config = self.tx_timeout_data();
         
         // Enable IRQs
         let irqs = Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT;
@@ -773,255 +1046,5272 @@ where
 
         // Enter transmit mode
         self.hal.write_cmd(Commands::SetTx as u8, &config)?;
+        self.state_changed(State::Tx);
 
         trace!("TX start issued");
 
+        self.activity(Activity::TxStart);
+
         let state = self.get_state()?;
         trace!("State: {:?}", state);
 
         Ok(())
     }
 
-    /// Check for transmit completion
-    fn check_transmit(&mut self) -> Result<bool, Self::Error> {
-        // Poll on DIO and short-circuit if not asserted
-        #[cfg(feature = "poll_irq")]
-        if self.hal.get_dio()? == PinState::Low {
-            return Ok(false)
-        }
+    /// Transmit a sequence of packets, waiting for each to complete (up to `Config.timeout_ms`)
+    /// before starting the next, and returning the number of packets sent
+    ///
+    /// On failure this returns the index of the failing packet (equal to the number of packets
+    /// sent) along with the error, `Error::Timeout` where a packet did not complete in time.
+    pub fn transmit_all<'a, I>(&mut self, packets: I) -> Result<usize, (usize, Error<CommsError, PinError, DelayError>)>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        use radio::Transmit;
+
+        let irqs = Irq::TX_DONE | Irq::RX_TX_TIMEOUT;
+        let timeout_ms = self.config.timeout_ms;
+        let mut sent = 0;
+
+        for p in packets {
+            let res = self.start_transmit(p)
+                .and_then(|_| self.wait_irq(irqs, timeout_ms) )
+                .and_then(|irq| match irq.contains(Irq::TX_DONE) {
+                    true => Ok(()),
+                    false => Err(Error::Timeout),
+                });
+
+            if let Err(e) = res {
+                warn!("TX of packet {} failed: {:?}", sent, e);
+                return Err((sent, e))
+            }
+
+            sent += 1;
+        }
+
+        debug!("TX sent {} packets", sent);
+
+        Ok(sent)
+    }
+
+    /// Start LoRa channel activity detection (CAD) over the provided number of symbols,
+    /// see `poll_cad` to check for completion
+    ///
+    /// The SX1280 always returns to STDBY_RC on CAD completion, so `CadExitMode::Rx` is
+    /// implemented by priming the `SetRx` timeout here and entering receive mode from `poll_cad`
+    /// as soon as activity is detected. Returns `Error::InvalidConfiguration` if not configured
+    /// for LoRa mode.
+    pub fn start_cad(&mut self, symbols: LoRaCadSymbols, exit: CadExitMode) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if !matches!(self.config.channel, Channel::LoRa(_)) {
+            warn!("Channel activity detection requires a LoRa channel (channel: {:?})", self.config.channel);
+            return Err(Error::InvalidConfiguration)
+        }
+
+        self.set_state(State::StandbyRc)?;
+
+        self.hal.write_cmd(Commands::SetCadParams as u8, &[ symbols as u8 ])?;
+
+        let irqs = Irq::CAD_DONE | Irq::CAD_ACTIVITY_DETECTED;
+        self.set_irq_dio_mask(irqs, irqs, DioMask::empty(), DioMask::empty())?;
+
+        self.cad_rx = match exit {
+            CadExitMode::Rx => Some(self.rx_timeout_data()),
+            CadExitMode::StandbyRc => None,
+        };
+
+        debug!("CAD start (symbols: {:?}, exit: {:?})", symbols, exit);
+
+        self.hal.write_cmd(Commands::SetCad as u8, &[])
+    }
+
+    /// Poll for CAD completion, returning `None` while CAD is in progress
+    ///
+    /// Where CAD was started with `CadExitMode::Rx` and activity is detected, the device is
+    /// switched to receive mode (using the primed timeout) and `CadEvent::Receiving` returned,
+    /// reception should then be checked with `radio::Receive::check_receive`.
+    pub fn poll_cad(&mut self) -> Result<Option<CadEvent>, Error<CommsError, PinError, DelayError>> {
+        let irq = self.get_interrupts(true)?;
+
+        if !irq.contains(Irq::CAD_DONE) {
+            return Ok(None)
+        }
+
+        let detected = irq.contains(Irq::CAD_ACTIVITY_DETECTED);
+
+        debug!("CAD complete (activity: {})", detected);
+
+        match (detected, self.cad_rx.take()) {
+            (true, Some(timeout)) => {
+                let (_, rx_base) = self.buffer_base();
+                self.rx_queue = RxQueue::default();
+                self.rx_queue.next = rx_base;
+
+                let irqs = Self::rx_irqs();
+                self.set_irq_dio_mask(irqs, irqs, DioMask::empty(), DioMask::empty())?;
+
+                self.hal.write_cmd(Commands::SetRx as u8, &timeout)?;
+                self.rx_settle_us = Some(self.config.rx_settle_us);
+                self.preamble_detected = false;
+                self.state_changed(State::Rx);
+
+                self.activity(Activity::RxStart);
+
+                Ok(Some(CadEvent::Receiving))
+            },
+            (true, None) => Ok(Some(CadEvent::Detected)),
+            (false, _) => Ok(Some(CadEvent::Clear)),
+        }
+    }
+
+    /// Transmit a LoRa packet once the channel is clear (CSMA), returning whether the packet was sent
+    ///
+    /// Channel activity detection is run prior to transmission, on detecting activity this backs off
+    /// for a random number of symbol periods (1 to `CSMA_BACKOFF_SYMBOLS_MAX`, from the provided
+    /// `rng` function) and retries, giving up after `max_backoffs` backoffs. On a clear channel the
+    /// packet is sent with `start_transmit`, completion should be checked with `check_transmit`.
+    pub fn transmit_csma<R>(&mut self, data: &[u8], max_backoffs: u8, mut rng: R) -> Result<bool, Error<CommsError, PinError, DelayError>>
+    where
+        R: FnMut() -> u8,
+    {
+        use radio::Transmit;
+
+        let symbol_us = match &self.config.channel {
+            Channel::LoRa(c) => c.symbol_time_us(),
+            _ => {
+                warn!("CSMA requires a LoRa channel (channel: {:?})", self.config.channel);
+                return Err(Error::InvalidConfiguration)
+            }
+        };
+
+        for attempt in 0..=max_backoffs {
+            if !self.channel_activity(LoRaCadSymbols::Cad04Symbols)? {
+                self.start_transmit(data)?;
+                return Ok(true)
+            }
+
+            if attempt == max_backoffs {
+                break;
+            }
+
+            let backoff = (rng() % CSMA_BACKOFF_SYMBOLS_MAX) as u32 + 1;
+
+            debug!("CSMA channel busy, backing off {} symbols", backoff);
+
+            self.hal.try_delay_us(backoff * symbol_us).map_err(Error::Delay)?;
+        }
+
+        debug!("CSMA channel busy, giving up after {} backoffs", max_backoffs);
+
+        Ok(false)
+    }
+
+    /// Switch modem type at runtime, updating only the packet type, modulation, and packet parameters
+    ///
+    /// The current operating frequency and PA configuration are retained (the frequency of the
+    /// provided channel is ignored), allowing devices to alternate between protocols without
+    /// a full `configure`. The modem and channel pairing is validated prior to any device writes.
+    pub fn set_modem(&mut self, modem: Modem, channel: Channel) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let mut channel = channel;
+        channel.set_frequency(self.config.channel.frequency());
+
+        let mut config = self.config.clone();
+        config.modem = modem.clone();
+        config.channel = channel.clone();
+        config.validate()?;
+
+        self.require_state(&CONFIG_STATES)?;
+
+        debug!("Switching modem (modem: {:?}, channel: {:?})", modem, channel);
+
+        let packet_type = PacketType::from(&channel);
+        let packet_data = [ packet_type as u8 ];
+        let mod_data = channel.modulation_params();
+
+        if self.packet_type != packet_type {
+            self.write_cmds_checked(&[
+                (Commands::SetPacketType as u8, &packet_data),
+                (Commands::SetModulationParams as u8, &mod_data),
+            ])?;
+            self.packet_type = packet_type;
+        } else {
+            self.write_cmds_checked(&[
+                (Commands::SetModulationParams as u8, &mod_data),
+            ])?;
+        }
+
+        self.write_modem(&modem)?;
+
+        self.config.modem = modem;
+        self.config.channel = channel;
+
+        Ok(())
+    }
+
+    /// Read raw command data from the device (see `ll` module)
+    pub fn read_command(&mut self, command: u8, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.hal.read_cmd(command, data)
+    }
+
+    /// Write a raw command to the device (see `ll` module)
+    pub fn write_command(&mut self, command: u8, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.hal.write_cmd(command, data)
+    }
+
+    /// Read raw register(s) from the device (see `ll` module)
+    pub fn read_register(&mut self, reg: u16, data: &mut [u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.hal.read_regs(reg, data)
+    }
+
+    /// Write raw register(s) to the device (see `ll` module)
+    pub fn write_register(&mut self, reg: u16, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.hal.write_regs(reg, data)
+    }
+
+    /// Poll for the current channel RSSI without checking the device state
+    ///
+    /// Values read outside of receive mode are not meaningful, see `radio::Rssi::poll_rssi`
+    /// for a checked alternative.
+    pub fn poll_rssi_unchecked(&mut self) -> Result<i16, Error<CommsError, PinError, DelayError>> {
+        self.await_rx_settle()?;
+
+        let mut raw = [0u8; 1];
+        self.hal.read_cmd(Commands::GetRssiInst as u8, &mut raw)?;
+        Ok(-(raw[0] as i16) / 2)
+    }
+
+    /// Compute the theoretical receiver sensitivity in dBm for the configured LoRa channel,
+    /// see `LoRaChannel::sensitivity_dbm`
+    ///
+    /// Returns `Error::InvalidConfiguration` if not configured for LoRa or ranging mode.
+    #[cfg(feature = "float")]
+    pub fn lora_sensitivity_dbm(&self) -> Result<f32, Error<CommsError, PinError, DelayError>> {
+        match &self.config.channel {
+            Channel::LoRa(c) | Channel::Ranging(c) => Ok(c.sensitivity_dbm()),
+            _ => Err(Error::InvalidConfiguration),
+        }
+    }
+
+    /// Scan a set of channels, sampling the instantaneous RSSI on each frequency after
+    /// dwelling in receive mode for `dwell_us` microseconds
+    ///
+    /// Frequency / RSSI pairs are written to `results`, returning the number of channels scanned
+    /// (limited by the length of `results`). The device is left in STDBY_RC tuned to the
+    /// configured channel frequency.
+    pub fn scan_channels(&mut self, freqs: &[u32], dwell_us: u32, results: &mut [(u32, i16)]) -> Result<usize, Error<CommsError, PinError, DelayError>> {
+        use radio::State as _;
+
+        let mut n = 0;
+
+        for (f, r) in freqs.iter().zip(results.iter_mut()) {
+            self.set_state(State::StandbyRc)?;
+            self.set_frequency(*f)?;
+
+            // Enter receive mode with the RX timeout, as for `start_receive`
+            let timeout = self.rx_timeout_data();
+            self.hal.write_cmd(Commands::SetRx as u8, &timeout)?;
+            self.rx_settle_us = Some(self.config.rx_settle_us);
+            self.state_changed(State::Rx);
+
+            self.hal.try_delay_us(dwell_us).map_err(Error::Delay)?;
+            self.rx_settle_us = self.rx_settle_us.map(|t| t.saturating_sub(dwell_us) );
+
+            *r = (*f, self.poll_rssi_unchecked()?);
+            trace!("Scan {} Hz: {} dBm", r.0, r.1);
+
+            n += 1;
+        }
+
+        // Restore the configured channel frequency
+        let original = self.config.channel.frequency();
+        self.set_state(State::StandbyRc)?;
+        self.set_frequency(original)?;
+
+        Ok(n)
+    }
+
+    /// Wait out any remaining RX settle time following entry to receive mode
+    fn await_rx_settle(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        match self.rx_settle_us.take() {
+            Some(t) if t > 0 => {
+                trace!("Awaiting RX settle ({} us)", t);
+                self.hal.try_delay_us(t).map_err(Error::Delay)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the RSSI threshold in dBm for carrier detection (GFSK and FLRC modes only)
+    ///
+    /// The SX128x has no hardware RSSI threshold or interrupt, so the threshold is stored
+    /// (in the device `-2 × raw` RSSI encoding) and compared against instantaneous RSSI
+    /// measurements by `carrier_detect`. Returns `Error::InvalidConfiguration` for LoRa
+    /// and ranging modes, or where the threshold cannot be encoded.
+    pub fn set_rssi_threshold(&mut self, dbm: i16) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        match self.config.modem {
+            Modem::Gfsk(_) | Modem::Flrc(_) => (),
+            _ => {
+                warn!("RSSI threshold is not supported for modem: {:?}", self.config.modem);
+                return Err(Error::InvalidConfiguration)
+            }
+        }
+
+        let raw = match rssi_to_raw(dbm) {
+            Some(r) => r,
+            None => {
+                warn!("Invalid RSSI threshold {} dBm (expected -127 to 0 dBm)", dbm);
+                return Err(Error::InvalidConfiguration)
+            }
+        };
+
+        trace!("Set RSSI threshold {} dBm (raw: 0x{:02x})", dbm, raw);
+
+        self.rssi_threshold = Some(raw);
+
+        Ok(())
+    }
+
+    /// Check whether the instantaneous RSSI is at or above the configured threshold
+    ///
+    /// The device must be in receive mode for the measurement to be valid, returns
+    /// `Error::InvalidConfiguration` if no threshold has been set with `set_rssi_threshold`.
+    pub fn carrier_detect(&mut self) -> Result<bool, Error<CommsError, PinError, DelayError>> {
+        let threshold = match self.rssi_threshold {
+            Some(t) => t,
+            None => return Err(Error::InvalidConfiguration),
+        };
+
+        self.await_rx_settle()?;
+
+        let mut raw = [0u8; 1];
+        self.hal.read_cmd(Commands::GetRssiInst as u8, &mut raw)?;
+
+        // Raw values increase as signal strength decreases
+        Ok(raw[0] <= threshold)
+    }
+
+    /// Save the device register context
+    ///
+    /// This captures the current configuration along with sync word, CRC, whitening
+    /// and gain registers, for restoring with `restore_context` following a wakeup
+    /// from sleep without data retention.
+    pub fn save_context(&mut self) -> Result<RegContext, Error<CommsError, PinError, DelayError>> {
+        let mut ctx = RegContext {
+            config: self.config.clone(),
+            sync_words: [[0u8; 5]; 3],
+            sync_word_tolerance: 0,
+            crc_seed: [0u8; 2],
+            crc_poly: [0u8; 2],
+            whitening_seed: 0,
+            lna_regime: 0,
+            gain_control: [0u8; 3],
+        };
+
+        self.hal.read_regs(Registers::LrSyncWordBaseAddress1 as u16, &mut ctx.sync_words[0])?;
+        self.hal.read_regs(Registers::LrSyncWordBaseAddress2 as u16, &mut ctx.sync_words[1])?;
+        self.hal.read_regs(Registers::LrSyncWordBaseAddress3 as u16, &mut ctx.sync_words[2])?;
+        ctx.sync_word_tolerance = self.hal.read_reg(Registers::LrSyncWordTolerance as u16)?;
+
+        self.hal.read_regs(Registers::LrCrcSeedBaseAddr as u16, &mut ctx.crc_seed)?;
+        self.hal.read_regs(Registers::LrCrcPolyBaseAddr as u16, &mut ctx.crc_poly)?;
+        ctx.whitening_seed = self.hal.read_reg(Registers::LrWhitSeedBaseAddr as u16)?;
+
+        ctx.lna_regime = self.hal.read_reg(Registers::LnaRegime as u16)?;
+        ctx.gain_control[0] = self.hal.read_reg(Registers::EnableManuaLGainControl as u16)?;
+        ctx.gain_control[1] = self.hal.read_reg(Registers::DemodDetection as u16)?;
+        ctx.gain_control[2] = self.hal.read_reg(Registers::ManualGainValue as u16)?;
+
+        trace!("Saved context: {:?}", ctx);
+
+        Ok(ctx)
+    }
+
+    /// Restore a device register context saved with `save_context`
+    ///
+    /// This must be called in standby mode (ie. following `wakeup`), and writes the regulator,
+    /// channel, packet, and PA configurations prior to the raw register values.
+    pub fn restore_context(&mut self, ctx: &RegContext) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Restoring context: {:?}", ctx);
+
+        let config = &ctx.config;
+
+        // Packet type is lost without retention, force this to be re-written
+        self.packet_type = PacketType::None;
+
+        self.write_regulator_mode(config.regulator_mode, config.pa_config.power)?;
+        self.write_channel(&config.channel)?;
+        self.write_modem(&config.modem)?;
+        self.set_power_ramp(config.pa_config.power, config.pa_config.ramp_time)?;
+
+        self.hal.write_regs(Registers::LrSyncWordBaseAddress1 as u16, &ctx.sync_words[0])?;
+        self.hal.write_regs(Registers::LrSyncWordBaseAddress2 as u16, &ctx.sync_words[1])?;
+        self.hal.write_regs(Registers::LrSyncWordBaseAddress3 as u16, &ctx.sync_words[2])?;
+        self.hal.write_reg(Registers::LrSyncWordTolerance as u16, ctx.sync_word_tolerance)?;
+
+        self.hal.write_regs(Registers::LrCrcSeedBaseAddr as u16, &ctx.crc_seed)?;
+        self.hal.write_regs(Registers::LrCrcPolyBaseAddr as u16, &ctx.crc_poly)?;
+        self.hal.write_reg(Registers::LrWhitSeedBaseAddr as u16, ctx.whitening_seed)?;
+
+        self.hal.write_reg(Registers::LnaRegime as u16, ctx.lna_regime)?;
+        self.hal.write_reg(Registers::EnableManuaLGainControl as u16, ctx.gain_control[0])?;
+        self.hal.write_reg(Registers::DemodDetection as u16, ctx.gain_control[1])?;
+        self.hal.write_reg(Registers::ManualGainValue as u16, ctx.gain_control[2])?;
+
+        self.config = ctx.config.clone();
+
+        Ok(())
+    }
+
+    pub fn firmware_version(&mut self) -> Result<u16, Error<CommsError, PinError, DelayError>> {
+        let mut d = [0u8; 2];
+
+        self.hal.read_regs(Registers::LrFirmwareVersionMsb as u16, &mut d)?;
+
+        Ok((d[0] as u16) << 8 | (d[1] as u16))
+    }
+
+    /// Set the operating frequency in Hz
+    ///
+    /// Returns `Error::InvalidFrequency` where the computed PLL step count falls outside
+    /// of the `FREQ_MIN` to `FREQ_MAX` band (or the 24-bit `SetRfFrequency` field),
+    /// without writing to the device.
+    pub fn set_frequency(&mut self, f: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.config.freq_to_steps_checked(f)?;
+
+        self.recalibrate_for(f)?;
+
+        let data = self.frequency_data(f);
+
+        self.hal.write_cmd(Commands::SetRfFrequency as u8, &data)?;
+        self.last_freq = Some(f);
+
+        Ok(())
+    }
+
+    /// Set the operating frequency, correcting for reference crystal (or TCXO) temperature drift
+    ///
+    /// The SX128x does not expose a temperature sensor, so the current temperature is read via
+    /// the provided `read_temp_c` function. The reference drift is computed from `ppm_per_c` relative
+    /// to `ref_temp_c` and the programmed frequency offset to compensate. If the temperature cannot
+    /// be read (`read_temp_c` returns `None`) this falls back to `set_frequency` with no correction.
+    #[cfg(feature = "float")]
+    pub fn set_frequency_compensated<T>(&mut self, f: u32, ppm_per_c: f32, ref_temp_c: i16, read_temp_c: T) -> Result<(), Error<CommsError, PinError, DelayError>>
+    where
+        T: FnOnce() -> Option<i16>,
+    {
+        let temp_c = match read_temp_c() {
+            Some(t) => t,
+            None => {
+                warn!("Temperature read failed, frequency not compensated");
+                return self.set_frequency(f)
+            }
+        };
+
+        // A reference running fast by `ppm` raises the RF frequency by the same ratio
+        let ppm = ppm_per_c * (temp_c - ref_temp_c) as f32;
+        let offset = (f as f32 * ppm / 1_000_000.0) as i64;
+        let corrected = (f as i64 - offset) as u32;
+
+        debug!("Compensating frequency {} Hz by {} Hz ({} C, {} ppm)", f, -offset, temp_c, ppm);
+
+        self.set_frequency(corrected)
+    }
+
+    /// Hop to the channel at the provided index in a precomputed hop table
+    ///
+    /// This writes the cached `SetRfFrequency` data directly, skipping frequency conversion,
+    /// with frequencies validated on constructing the table (see `HopTable::new`).
+    pub fn hop_to<const N: usize>(&mut self, table: &HopTable<N>, index: usize) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let data = match table.get(index) {
+            Some(d) => *d,
+            None => {
+                warn!("Hop index {} out of range for table of {} channels", index, table.len());
+                return Err(Error::InvalidConfiguration)
+            }
+        };
+
+        trace!("Hopping to channel {} ({:02x?})", index, data);
+
+        let steps = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+        let f = self.config.steps_to_freq(steps);
+
+        self.recalibrate_for(f)?;
+
+        self.hal.write_cmd(Commands::SetRfFrequency as u8, &data)?;
+        self.last_freq = Some(f);
+
+        Ok(())
+    }
+
+    /// Re-run ADC and PLL calibration if the provided frequency differs from the last
+    /// configured frequency by more than `RECALIBRATION_DELTA_HZ`
+    ///
+    /// Calibration is only accepted in STDBY_RC, so from STDBY_XOSC or FS the device is switched
+    /// to STDBY_RC for calibration then returned to the prior state. In other states (where the
+    /// frequency should not be changed) calibration is skipped.
+    fn recalibrate_for(&mut self, f: u32) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let delta = match self.last_freq {
+            Some(last) => f.abs_diff(last),
+            None => return Ok(()),
+        };
+
+        if delta <= RECALIBRATION_DELTA_HZ {
+            return Ok(())
+        }
+
+        let state = self.get_state()?;
+        if !CONFIG_STATES.contains(&state) {
+            warn!("Skipping recalibration for frequency change ({} Hz) in state {:?}", delta, state);
+            return Ok(())
+        }
+
+        debug!("Recalibrating for frequency change ({} Hz)", delta);
+
+        if state != State::StandbyRc {
+            self.set_state(State::StandbyRc)?;
+        }
+
+        self.calibrate(CalibrationParams::ADCBulkPEnable | CalibrationParams::ADCBulkNEnable
+            | CalibrationParams::ADCPulseEnable | CalibrationParams::PLLEnable)?;
+
+        if state != State::StandbyRc {
+            self.set_state(state)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the operating frequency in MHz, a convenience wrapper around `set_frequency`
+    pub fn set_frequency_mhz(&mut self, mhz: u16) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let f = (mhz as u32).checked_mul(1_000_000).ok_or(Error::InvalidFrequency)?;
+
+        self.set_frequency(f)
+    }
+
+    /// Set the operating frequency from a channel plan, as `base_hz + index * spacing_hz`
+    ///
+    /// Returns `Error::InvalidFrequency` where the resulting frequency is outside of
+    /// `FREQ_MIN` to `FREQ_MAX`.
+    pub fn set_channel_index(&mut self, base_hz: u32, spacing_hz: u32, index: u16) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let f = base_hz as u64 + index as u64 * spacing_hz as u64;
+
+        if f < FREQ_MIN as u64 || f > FREQ_MAX as u64 {
+            warn!("Channel index {} ({} Hz) out of band", index, f);
+            return Err(Error::InvalidFrequency)
+        }
+
+        self.set_frequency(f as u32)
+    }
+
+    /// Fetch the driver's cached operating frequency, as quantised to PLL steps
+    ///
+    /// The SX128x does not document readable frequency registers, so this is not read back
+    /// from the device but reconstructed from the last successful `SetRfFrequency` written
+    /// by the driver (or the configured channel where no frequency has been set).
+    pub fn frequency(&self) -> u32 {
+        let f = self.last_freq.unwrap_or_else(|| self.config.channel.frequency());
+
+        let steps = self.config.freq_to_steps_u64(f);
+
+        self.config.steps_to_freq(steps)
+    }
+
+    /// Compute `SetRfFrequency` command data for a given frequency
+    fn frequency_data(&self, f: u32) -> [u8; 3] {
+        let c = self.config.freq_to_steps_u64(f);
+
+        trace!("Setting frequency ({:?} MHz, {} index)", f / 1000 / 1000, c);
+
+        [
+            (c >> 16) as u8,
+            (c >> 8) as u8,
+            c as u8,
+        ]
+    }
+
+    pub (crate) fn set_power_ramp(&mut self, power: i8, ramp: RampTime) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        
+        if power > TX_POWER_MAX || power < TX_POWER_MIN {
+            warn!("TX power out of range (-18 < p < 13)");
+        }
+
+        // Limit to -18 to +13 dBm
+        let power = core::cmp::max(power, TX_POWER_MIN);
+        let power = core::cmp::min(power, TX_POWER_MAX);
+        let power_reg = (power - TX_POWER_MIN) as u8;
+
+        trace!("Setting TX power to {} dBm {:?} ramp ({}, {})", power, ramp, power_reg, ramp as u8);
+        self.config.pa_config.power = power;
+        self.config.pa_config.ramp_time = ramp;
+
+        self.hal.write_cmd(Commands::SetTxParams as u8, &[ power_reg, ramp as u8 ])
+    }
+
+    /// Set TX power in dBm, returning the applied power
+    ///
+    /// Out-of-range values are clamped to the supported -18 to +13 dBm range,
+    /// or rejected with `Error::InvalidConfiguration` if `Config.strict_power` is set.
+    pub fn set_power_checked(&mut self, power: i8) -> Result<i8, Error<CommsError, PinError, DelayError>> {
+        let ramp_time = self.config.pa_config.ramp_time;
+        self.set_pa_config(power, ramp_time)?;
+
+        Ok(self.config.pa_config.power)
+    }
+
+    /// Configure the power amplifier output power (in dBm) and ramp time,
+    /// persisting the applied configuration to `Config.pa_config`
+    ///
+    /// Unlike the SX126x the SX128x PA has no duty cycle or clamping registers, output power is
+    /// set solely by `SetTxParams` over -18 to +13 dBm in 1 dB steps, and any ramp time is valid
+    /// at any power. Out-of-range powers are clamped with a warning, or rejected with
+    /// `Error::InvalidConfiguration` if `Config.strict_power` is set.
+    pub fn set_pa_config(&mut self, dbm: i8, ramp: RampTime) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if self.config.strict_power && !(TX_POWER_MIN..=TX_POWER_MAX).contains(&dbm) {
+            warn!("TX power {} dBm out of range ({} to {} dBm)", dbm, TX_POWER_MIN, TX_POWER_MAX);
+            return Err(Error::InvalidConfiguration)
+        }
+
+        self.set_power_ramp(dbm, ramp)?;
+
+        // Re-evaluate automatic regulator selection for the new power
+        if self.config.regulator_mode == RegulatorMode::Auto {
+            let power = self.config.pa_config.power;
+            self.write_regulator_mode(RegulatorMode::Auto, power)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set IRQ mask
+    pub fn set_irq_mask(&mut self, irq: Irq) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Setting IRQ mask: {:?}", irq);
+
+        let raw = irq.bits();
+        self.hal.write_cmd(Commands::SetDioIrqParams as u8, &[ (raw >> 8) as u8, (raw & 0xff) as u8])
+    }
+
+    /// Set the IRQ and DIO masks
+    pub fn set_irq_dio_mask(&mut self, irq: Irq, dio1: DioMask, dio2: DioMask, dio3: DioMask) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Setting IRQ mask: {:?} DIOs: {:?}, {:?}, {:?}", irq, dio1, dio2, dio3);
+
+        let raw_irq = irq.bits();
+        let raw_dio1 = dio1.bits();
+        let raw_dio2 = dio2.bits();
+        let raw_dio3 = dio3.bits();
+
+        let data = [ 
+            (raw_irq >> 8) as u8, 
+            (raw_irq & 0xff) as u8,
+
+            (raw_dio1 >> 8) as u8, 
+            (raw_dio1 & 0xff) as u8,
+            (raw_dio2 >> 8) as u8, 
+            (raw_dio2 & 0xff) as u8,
+            (raw_dio3 >> 8) as u8, 
+            (raw_dio3 & 0xff) as u8,
+        ];
+
+        self.hal.write_cmd(Commands::SetDioIrqParams as u8, &data)
+    }
+
+    /// Check the device is in one of the `allowed` states, returning `Error::InvalidState` otherwise
+    ///
+    /// This is bypassed where `Config.unsafe_state_transitions` is set.
+    pub(crate) fn require_state(&mut self, allowed: &[State]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if self.config.unsafe_state_transitions {
+            return Ok(())
+        }
+
+        let state = self.get_state()?;
+        self.check_state(state, allowed)
+    }
+
+    /// Check a previously fetched state against the `allowed` states (see `require_state`)
+    fn check_state(&self, state: State, allowed: &[State]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if self.config.unsafe_state_transitions || allowed.contains(&state) {
+            return Ok(())
+        }
+
+        warn!("Operation not permitted in state: {:?} (allowed: {:?})", state, allowed);
+        Err(Error::InvalidState(allowed[0], state))
+    }
+
+    /// Configure the modem (packet parameters), requiring the device be in a standby or FS state
+    pub(crate) fn configure_modem(&mut self, config: &Modem) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.require_state(&CONFIG_STATES)?;
+        self.write_modem(config)
+    }
+
+    /// Write modem (packet parameter) configuration without checking the device state
+    fn write_modem(&mut self, config: &Modem) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        use Modem::*;
+
+        debug!("Setting modem config: {:?}", config);
+
+        // Invalidate any previously applied RX configuration
+        self.rx_configured.take();
+
+        // First update packet type (if required)
+        let packet_type = PacketType::from(config);
+        if self.packet_type != packet_type {
+            trace!("Setting packet type: {:?}", packet_type);
+            self.write_cmd_checked(Commands::SetPacketType as u8, &[ packet_type.clone() as u8 ] )?;
+            self.packet_type = packet_type;
+        }
+
+        let data = match config {
+            Gfsk(c) => [c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.whitening as u8],
+            LoRa(c) | Ranging(c) => [device::lora::encode_preamble_length(c.preamble_length), c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.invert_iq as u8, 0u8, 0u8],
+            Flrc(c) => [c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8, c.payload_length as u8, c.crc_mode as u8, c.whitening as u8],
+            Ble(c) => [c.connection_state as u8, c.crc_field as u8, c.packet_type as u8, c.whitening as u8, 0u8, 0u8, 0u8],
+            None => [0u8; 7],
+        };
+
+        self.hal.write_cmd(Commands::SetPacketParams as u8, &data)?;
+
+
+        // Apply patches
+        match config {
+            Flrc(c) if c.patch_syncword => {
+                // Apply sync-word patch for FLRC mode
+                self.patch_flrc_syncword()?;
+            },
+            Gfsk(c) if c.patch_preamble => {
+                // Write preamble length for GFSK mode
+                self.hal.write_reg(Registers::GfskBlePreambleLength as u16, c.preamble_length as u8)?;
+            },
+            _ => ()
+        }
+
+        // Check the device agrees on packet type
+        if self.config.verify {
+            self.verify_packet_type()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the ranging calibration constant for the current ranging channel configuration
+    ///
+    /// Returns `Error::InvalidConfiguration` if the channel is not a ranging channel, or the
+    /// spreading factor and bandwidth are not supported for ranging.
+    pub fn set_ranging_calibration(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let cal = match &self.config.channel {
+            Channel::Ranging(c) => device::lora::ranging_calibration(c.sf, c.bw),
+            _ => None,
+        };
+
+        match cal {
+            Some(c) => self.set_ranging_calibration_raw(c),
+            None => {
+                warn!("No ranging calibration available for channel: {:?}", self.config.channel);
+                Err(Error::InvalidConfiguration)
+            }
+        }
+    }
+
+    /// Write a raw ranging calibration constant, overriding the built-in calibration table
+    pub fn set_ranging_calibration_raw(&mut self, cal: u16) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Set ranging calibration: {}", cal);
+        self.hal.write_regs(Registers::LrRangingReRxTxDelayCal as u16, &[ (cal >> 8) as u8, (cal & 0xff) as u8 ])
+    }
+
+    /// Run a power-on self test, checking the firmware version, SPI register write / read-back,
+    /// STDBY_XOSC entry, and BUSY de-assertion, returning a report of the checks that passed.
+    ///
+    /// The scratch register is restored following the test and the device is left in STDBY_RC.
+    /// Communication errors are returned directly as these prevent any further checks.
+    pub fn self_test(&mut self) -> Result<SelfTestReport, Error<CommsError, PinError, DelayError>> {
+        use radio::State as _;
+
+        // Check the firmware version
+        let firmware_version = self.firmware_version()?;
+        let firmware = self.config.accepted_firmware_versions.contains(&firmware_version);
+
+        // Write and read back a scratch register
+        let original = self.hal.read_reg(SELF_TEST_REGISTER as u16)?;
+        self.hal.write_reg(SELF_TEST_REGISTER as u16, !original)?;
+        let readback = self.hal.read_reg(SELF_TEST_REGISTER as u16)? == !original;
+        self.hal.write_reg(SELF_TEST_REGISTER as u16, original)?;
+
+        // Switch to the crystal oscillator and check for errors
+        self.set_state(State::StandbyXosc)?;
+        let (state, status) = self.get_status()?;
+        let xosc = state == State::StandbyXosc && !matches!(status,
+            CommandStatus::Timeout | CommandStatus::ProcessingError | CommandStatus::ExecutionFailure);
+        self.set_state(State::StandbyRc)?;
+
+        // BUSY should be released once the device is idle
+        let busy = self.check_busy_line()?;
+
+        let report = SelfTestReport{ firmware_version, firmware, readback, xosc, busy };
+
+        debug!("Self test: {:?}", report);
+
+        Ok(report)
+    }
+
+    /// Set a hook to be called on TX and RX activity (for example to drive activity LEDs),
+    /// invoked on entering TX or RX modes and on completion (via `check_transmit`,
+    /// `check_receive` or `wait_irq`)
+    ///
+    /// This takes a function pointer so as to be usable without allocation.
+    pub fn set_activity_hook(&mut self, hook: fn(Activity)) {
+        self.activity_hook = Some(hook);
+    }
+
+    /// Remove a previously set activity hook
+    pub fn clear_activity_hook(&mut self) {
+        self.activity_hook = None;
+    }
+
+    /// Report an activity event to the activity hook, if set
+    fn activity(&self, activity: Activity) {
+        if let Some(hook) = self.activity_hook {
+            hook(activity);
+        }
+    }
+
+    /// Set a hook to be called with the previous and new states whenever the driver
+    /// requests a state change (via `set_state`, `sleep` / `wakeup`, `reset`, or entering TX or RX),
+    /// for reconstructing state sequences when debugging protocols built on the driver
+    pub fn set_state_change_hook(&mut self, hook: fn(from: State, to: State)) {
+        self.on_state_change = Some(hook);
+    }
+
+    /// Remove a previously set state change hook
+    pub fn clear_state_change_hook(&mut self) {
+        self.on_state_change = None;
+    }
+
+    /// Record a requested state, reporting changes to the state change hook if set
+    fn state_changed(&mut self, to: State) {
+        if to == self.last_state {
+            return
+        }
+
+        if let Some(hook) = self.on_state_change {
+            hook(self.last_state, to);
+        }
+
+        self.last_state = to;
+    }
+
+    /// Sample the BUSY line over a short window, returning whether it de-asserts.
+    /// This should be called with the device idle (ie. in a standby mode) and does
+    /// not issue any commands, so a BUSY line stuck high (from a bad connection or
+    /// pin mapping) can be told apart from other communication faults.
+    pub fn check_busy_line(&mut self) -> Result<bool, Error<CommsError, PinError, DelayError>> {
+        for i in 0..BUSY_CHECK_SAMPLES {
+            if self.hal.get_busy()? == PinState::Low {
+                return Ok(true)
+            }
+
+            if i + 1 < BUSY_CHECK_SAMPLES {
+                self.hal.try_delay_us(BUSY_CHECK_INTERVAL_US).map_err(Error::Delay)?;
+            }
+        }
+
+        error!("BUSY line stuck high over {} us", BUSY_CHECK_SAMPLES * BUSY_CHECK_INTERVAL_US);
+
+        Ok(false)
+    }
+
+    /// Fetch the raw (unparsed) device status byte, for debugging
+    pub fn get_raw_status(&mut self) -> Result<u8, Error<CommsError, PinError, DelayError>> {
+        let mut d = [0u8; 1];
+        self.hal.read_cmd(Commands::GetStatus as u8, &mut d)?;
+
+        trace!("raw state: 0x{:.2x}", d[0]);
+
+        Ok(d[0])
+    }
+
+    /// Fetch device status, returning the current device state and status of the last command
+    pub fn get_status(&mut self) -> Result<(State, CommandStatus), Error<CommsError, PinError, DelayError>> {
+        let d = self.get_raw_status()?;
+
+        let mode = (d & 0b1110_0000) >> 5;
+        let m = State::try_from(mode).map_err(|_| Error::InvalidResponse(d) )?;
+
+        let status = (d & 0b0001_1100) >> 2;
+        let s = CommandStatus::try_from(status).map_err(|_| Error::InvalidResponse(d) )?;
+
+        trace!("get state: {:?} status: {:?}", m, s);
+
+        Ok((m, s))
+    }
+
+    /// Write a command, checking the resulting command status and retrying on failure
+    pub(crate) fn write_cmd_checked(&mut self, command: u8, data: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.write_cmds_checked(&[(command, data)])
+    }
+
+    /// Write a sequence of commands, checking the resulting command status and retrying
+    /// the sequence up to `Config.command_retries` times on failure
+    ///
+    /// Returns `Error::InvalidResponse` with the failing command status if retries are exhausted.
+    pub(crate) fn write_cmds_checked(&mut self, cmds: &[(u8, &[u8])]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let mut retries = 0;
+
+        loop {
+            self.hal.write_cmds(cmds)?;
+
+            let (state, status) = self.get_status()?;
+
+            match status {
+                CommandStatus::ProcessingError | CommandStatus::ExecutionFailure => (),
+                _ => return Ok(()),
+            }
+
+            if retries >= self.config.command_retries {
+                error!("Command failed after {} retries (state: {:?} status: {:?})", retries, state, status);
+                return Err(Error::InvalidResponse(status as u8))
+            }
+
+            warn!("Command failed (state: {:?} status: {:?}), retrying", state, status);
+            retries += 1;
+        }
+    }
+
+    /// Verify the device packet type matches the expected packet type,
+    /// returning `Error::InvalidResponse` on mismatch
+    pub fn verify_packet_type(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let mut d = [0u8; 1];
+        self.hal.read_cmd(Commands::GetPacketType as u8, &mut d)?;
+
+        if d[0] != self.packet_type as u8 {
+            error!("Packet type mismatch (expected: {:?} actual: 0x{:02x})", self.packet_type, d[0]);
+            return Err(Error::InvalidResponse(d[0]))
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the receive buffer status, returning the `(pointer, length)` of the last received packet
+    ///
+    /// This does not read the packet from the FIFO, see `radio::Receive::get_received` to fetch packet data.
+    pub fn rx_buffer_status(&mut self) -> Result<(u8, u8), Error<CommsError, PinError, DelayError>> {
+        use device::lora::LoRaHeader;
+        use device::common::GfskFlrcPacketLength;
+
+        let mut status = [0u8; 2];
+
+        self.hal.read_cmd(Commands::GetRxBufferStatus as u8, &mut status)?;
+
+        let len = match &self.config.modem {
+            Modem::LoRa(c) => {
+                match c.header_type {
+                    LoRaHeader::Implicit => self.hal.read_reg(Registers::LrPayloadLength as u16)?,
+                    LoRaHeader::Explicit => status[0],
+                }
+            },
+            // Fixed length packets report the configured payload length
+            Modem::Gfsk(c) if c.header_type == GfskFlrcPacketLength::Fixed => c.payload_length,
+            Modem::Flrc(c) if c.header_type == GfskFlrcPacketLength::Fixed => c.payload_length,
+            // BLE status[0] does not include 2-byte PDU header,
+            // bogus lengths would exceed the 256-byte buffer
+            Modem::Ble(_) => match status[0].checked_add(2) {
+                Some(len) => len,
+                None => {
+                    warn!("Invalid BLE packet length: {}", status[0]);
+                    return Err(Error::InvalidLength)
+                }
+            },
+            _ => status[0]
+        };
+
+        let rx_buff_ptr = status[1];
+
+        trace!("RX buffer ptr: {} len: {}", rx_buff_ptr, len);
+
+        Ok((rx_buff_ptr, len))
+    }
+
+    /// Fetch packet information (RSSI, SNR and status) for the last received packet
+    ///
+    /// This does not read the packet from the FIFO, see `radio::Receive::get_received` to fetch packet data.
+    pub fn packet_info(&mut self) -> Result<PacketInfo, Error<CommsError, PinError, DelayError>> {
+        let mut info = PacketInfo::default();
+
+        let mut data = [0u8; 5];
+        self.hal.read_cmd(Commands::GetPacketStatus as u8, &mut data)?;
+
+        info.packet_status = PacketStatus::from_bits_truncate(data[2]);
+        info.tx_rx_status = TxRxStatus::from_bits_truncate(data[3]);
+        info.sync_addr_status = data[4] & 0b0111;
+
+        let (rssi, snr) = self.packet_metrics(&data)?;
+        info.rssi = rssi;
+        info.snr = snr;
+
+        debug!("Info: {:?}", info);
+
+        Ok(info)
+    }
+
+    /// Fetch the RSSI (in dBm) and SNR (in dB, LoRa and Ranging modes only) of the last
+    /// received packet, using a single `GetPacketStatus` read
+    pub fn last_packet_metrics(&mut self) -> Result<(i16, Option<i16>), Error<CommsError, PinError, DelayError>> {
+        let mut data = [0u8; 5];
+        self.hal.read_cmd(Commands::GetPacketStatus as u8, &mut data)?;
+
+        self.packet_metrics(&data)
+    }
+
+    /// Parse RSSI and SNR from `GetPacketStatus` data for the current packet type,
+    /// returning `Error::InvalidConfiguration` where no packet type is configured
+    fn packet_metrics(&self, data: &[u8; 5]) -> Result<(i16, Option<i16>), Error<CommsError, PinError, DelayError>> {
+        let metrics = match self.packet_type {
+            PacketType::Gfsk | PacketType::Flrc | PacketType::Ble => {
+                let rssi = -(data[1] as i16) / 2;
+                let rssi_avg = -(data[0] as i16) / 2;
+                trace!("Raw RSSI: {}", rssi);
+                trace!("Average RSSI: {}", rssi_avg);
+                (rssi, None)
+            },
+            PacketType::LoRa | PacketType::Ranging => {
+                let rssi = -(data[0] as i16) / 2;
+                let snr = match data[1] < 128 {
+                    true => data[1] as i16 / 4,
+                    false => ( data[1] as i16 - 256 ) / 4
+                };
+                (rssi, Some(snr))
+            },
+            PacketType::None => {
+                warn!("Packet metrics unavailable with no packet type configured");
+                return Err(Error::InvalidConfiguration)
+            },
+        };
+
+        Ok(metrics)
+    }
+
+    /// Run calibration of the selected blocks, see `CalibrationParams` for builder methods
+    ///
+    /// Note the SX128x has no image calibration, only ADC, PLL, and RC oscillator blocks.
+    pub fn calibrate(&mut self, c: CalibrationParams) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Calibrate {:?}", c);
+        self.hal.write_cmd(Commands::Calibrate as u8, &[ c.bits() ])
+    }
+
+    /// Set the regulator mode
+    ///
+    /// `RegulatorMode::Auto` is resolved against the configured TX power, selecting the DC/DC
+    /// converter at or above `REGULATOR_DCDC_POWER_DBM` and the LDO otherwise, and is
+    /// re-evaluated on subsequent TX power changes.
+    pub fn set_regulator_mode(&mut self, r: RegulatorMode) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let power = self.config.pa_config.power;
+        self.write_regulator_mode(r, power)?;
+        self.config.regulator_mode = r;
+
+        Ok(())
+    }
+
+    /// Write the regulator mode, resolved for the provided TX power
+    fn write_regulator_mode(&mut self, r: RegulatorMode, power: i8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let resolved = r.resolve(power);
+
+        trace!("Set regulator mode {:?} (resolved: {:?})", r, resolved);
+
+        self.hal.write_cmd(Commands::SetRegulatorMode as u8, &[ resolved as u8 ])
+    }
+
+    /// Configure automatic transmission following a reception
+    ///
+    /// When enabled the device enters TX `timeout_us` microseconds after the end of
+    /// each received packet (using the buffer contents from the last transmission),
+    /// allowing low-latency acknowledgements without host intervention.
+    /// The device adds a fixed `AUTO_RX_TX_OFFSET` (33us) switching delay, which is
+    /// compensated for here, so timeouts shorter than this are rejected.
+    pub fn set_auto_tx(&mut self, a: AutoTx) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let data = match a {
+            AutoTx::Enabled(timeout_us) => {
+                let compensated = match timeout_us.checked_sub(AUTO_RX_TX_OFFSET) {
+                    Some(v) => v,
+                    None => {
+                        warn!("AutoTx timeout {} us is less than the minimum offset {} us", timeout_us, AUTO_RX_TX_OFFSET);
+                        return Err(Error::InvalidConfiguration)
+                    }
+                };
+                [(compensated >> 8) as u8, (compensated & 0xff) as u8]
+            },
+            AutoTx::Disabled => [0u8; 2],
+        };
+
+        trace!("Set AutoTx {:?}", a);
+        self.hal.write_cmd(Commands::SetAutoTx as u8, &data)
+    }
+
+    /// Enable or disable automatic frequency synthesis (AutoFs) mode
+    ///
+    /// When enabled the device returns to FS rather than STDBY_RC following a
+    /// transmission or reception, keeping the synthesizer locked and reducing
+    /// TX / RX turnaround time at the cost of increased idle current draw.
+    pub fn set_auto_fs(&mut self, enable: bool) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Set AutoFs {}", enable);
+        self.hal.write_cmd(Commands::SetAutoFs as u8, &[ enable as u8 ])
+    }
+
+    /// Enable or disable high sensitivity (boosted gain) receive mode
+    ///
+    /// This improves sensitivity by ~3dB at the cost of increased receive current,
+    /// and is persisted in the device configuration so it is re-applied on reconfiguration.
+    pub fn set_rx_boosted(&mut self, enable: bool) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Set RX boosted {}", enable);
+
+        let value = match enable {
+            true => MASK_LNA_REGIME,
+            false => 0,
+        };
+
+        self.hal.update_reg(Registers::LnaRegime as u16, MASK_LNA_REGIME, value)?;
+        self.config.rx_boosted = enable;
+
+        Ok(())
+    }
+
+    /// Configure the receiver LNA gain control mode
+    ///
+    /// `GainMode::Manual` fixes the LNA gain and disables AGC, making RSSI measurements repeatable
+    /// between packets. Note that reported RSSI is then only valid relative to the selected gain step
+    /// (and must be offset accordingly for absolute measurements), and strong signals may saturate
+    /// the receiver at higher gain steps. `GainMode::Auto` restores the default AGC behaviour.
+    pub fn set_lna_gain(&mut self, mode: GainMode) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Set LNA gain {:?}", mode);
+
+        match mode {
+            GainMode::Manual(gain) => {
+                if gain == 0 || gain > MANUAL_GAIN_MAX {
+                    warn!("Invalid manual gain {} (expected 1 to {})", gain, MANUAL_GAIN_MAX);
+                    return Err(Error::InvalidConfiguration)
+                }
+
+                self.hal.update_reg(Registers::EnableManuaLGainControl as u16, MASK_MANUAL_GAIN_CONTROL, MASK_MANUAL_GAIN_CONTROL)?;
+                self.hal.update_reg(Registers::DemodDetection as u16, !MASK_DEMOD_DETECTION, 0)?;
+                self.hal.update_reg(Registers::ManualGainValue as u16, !MASK_MANUAL_GAIN_VALUE, gain)?;
+            },
+            GainMode::Auto => {
+                self.hal.update_reg(Registers::EnableManuaLGainControl as u16, MASK_MANUAL_GAIN_CONTROL, 0)?;
+                self.hal.update_reg(Registers::DemodDetection as u16, !MASK_DEMOD_DETECTION, !MASK_DEMOD_DETECTION)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_buff_base_addr(&mut self, tx: u8, rx: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Set buff base address (tx: {}, rx: {})", tx, rx);
+        self.rx_configured = None;
+        self.hal.write_cmd(Commands::SetBufferBaseAddress as u8, &[ tx, rx ])
+    }
+
+    /// Set explicit TX and RX buffer base addresses, preserved across subsequent transmit and receive calls
+    ///
+    /// The device has a single 256-byte data buffer shared between TX and RX, with packets written
+    /// (or received) starting at the relevant base address and wrapping at the end of the buffer.
+    /// By default both bases are 0, allowing the full buffer for each packet. Distinct regions
+    /// (eg. `(128, 0)`) allow a TX packet to be staged while retaining received data, so long as
+    /// packets are limited to the space between the bases.
+    ///
+    /// Where a layout has been set with `set_buffer_layout` the bases are moved within it,
+    /// retaining the layout TX and RX lengths and rejecting overlapping regions.
+    pub fn set_buffer_base(&mut self, tx: u8, rx: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        match self.buffer_layout {
+            Some(l) => self.set_buffer_layout(&BufferLayout{ tx_base: tx, rx_base: rx, ..l }),
+            None => self.write_buffer_base(tx, rx),
+        }
+    }
+
+    /// Set TX and RX buffer base addresses from a layout, rejecting layouts where the
+    /// maximum TX and RX packets would overlap or exceed the buffer
+    ///
+    /// The layout is retained, limiting subsequent transmit payloads (and staged data)
+    /// to the TX region.
+    pub fn set_buffer_layout(&mut self, layout: &BufferLayout) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        layout.validate()?;
+        self.write_buffer_base(layout.tx_base, layout.rx_base)?;
+        self.buffer_layout = Some(*layout);
+
+        Ok(())
+    }
+
+    /// Write and persist explicit TX and RX buffer base addresses
+    fn write_buffer_base(&mut self, tx: u8, rx: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.set_buff_base_addr(tx, rx)?;
+        self.config.buffer_base = Some((tx, rx));
+
+        Ok(())
+    }
+
+    /// Check a TX packet (or staged data) at `offset` lies within the TX region of the
+    /// buffer layout where set, returning `Error::InvalidLength` otherwise
+    fn check_tx_region(&self, offset: u8, len: usize) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let l = match &self.buffer_layout {
+            Some(l) => l,
+            None => return Ok(()),
+        };
+
+        let (start, end) = (l.tx_base as usize, l.tx_base as usize + l.tx_len as usize);
+        if (offset as usize) < start || offset as usize + len > end {
+            warn!("TX data (offset: {} length: {}) outside TX buffer region ({} to {})", offset, len, start, end);
+            return Err(Error::InvalidLength)
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the current TX and RX buffer base addresses
+    pub fn buffer_base(&self) -> (u8, u8) {
+        self.config.buffer_base.unwrap_or((0, 0))
+    }
+
+    /// Set the number of bit errors (0-7) tolerated when matching GFSK sync words
+    ///
+    /// Higher tolerances reduce missed packets at the cost of more false syncs.
+    /// Note that FLRC mode forces this to zero when `patch_syncword` is set.
+    pub fn set_gfsk_sync_tolerance(&mut self, bit_errors: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if bit_errors > SYNC_WORD_TOLERANCE_MAX {
+            warn!("Invalid sync word tolerance: {} (max {})", bit_errors, SYNC_WORD_TOLERANCE_MAX);
+            return Err(Error::InvalidConfiguration)
+        }
+
+        trace!("Set sync word tolerance: {}", bit_errors);
+
+        self.hal.update_reg(Registers::LrSyncWordTolerance as u16, MASK_SYNC_WORD_TOLERANCE, bit_errors)?;
+
+        Ok(())
+    }
+
+    /// Set the sychronization mode for a given index (1-3).
+    /// This is 5-bytes for GFSK mode and 4-bytes for FLRC and BLE modes.
+    pub fn set_syncword(&mut self, index: u8, value: &[u8]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        trace!("Attempting to set sync word index: {} to: {:?}", index, value);
+
+        // Check sync words for errata 16.4
+        if self.packet_type == PacketType::Flrc {
+            match &value[0..2] {
+                &[0x8C, 0x32] | &[0x63, 0x0E] => {
+                    error!("Invalid sync word selected (see errata 16.4)");
+                    return Err(Error::InvalidConfiguration);
+                },
+                _ => (),
+            }
+        }
+
+        // Calculate sync word base address and expected length
+        let (addr, len) = match self.syncword_location(index) {
+            Some(v) => v,
+            None => {
+                warn!("Invalid sync word configuration (mode: {:?} index: {} value: {:?}", self.config.modem, index, value);
+                return Err(Error::InvalidConfiguration)
+            }
+        };
+
+        // Check length is correct
+        if value.len() != len {
+            warn!("Incorrect sync word length for mode: {:?} (actual: {}, expected: {})", self.config.modem, value.len(), len);
+            return Err(Error::InvalidConfiguration)
+        }
+
+        // Write sync word
+        self.hal.write_regs(addr, value)?;
+
+        Ok(())
+    }
+
+    /// Read back the sychronization word for a given index (1-3) into `value`,
+    /// returning the sync word length for the current packet type (see `set_syncword`)
+    pub fn get_syncword(&mut self, index: u8, value: &mut [u8]) -> Result<usize, Error<CommsError, PinError, DelayError>> {
+        let (addr, len) = match self.syncword_location(index) {
+            Some(v) => v,
+            None => {
+                warn!("Invalid sync word configuration (mode: {:?} index: {})", self.config.modem, index);
+                return Err(Error::InvalidConfiguration)
+            }
+        };
+
+        if value.len() < len {
+            warn!("Sync word buffer too short for mode: {:?} (actual: {}, expected: {})", self.config.modem, value.len(), len);
+            return Err(Error::InvalidLength)
+        }
+
+        self.hal.read_regs(addr, &mut value[..len])?;
+
+        trace!("Read sync word index: {} value: {:?}", index, &value[..len]);
+
+        Ok(len)
+    }
+
+    /// Fetch the sync word register address and length for a given index and the current packet type
+    fn syncword_location(&self, index: u8) -> Option<(u16, usize)> {
+        match (&self.packet_type, index) {
+            (PacketType::Gfsk, 1) => Some((Registers::LrSyncWordBaseAddress1 as u16, 5)),
+            (PacketType::Gfsk, 2) => Some((Registers::LrSyncWordBaseAddress2 as u16, 5)),
+            (PacketType::Gfsk, 3) => Some((Registers::LrSyncWordBaseAddress3 as u16, 5)),
+            (PacketType::Flrc, 1) => Some((Registers::LrSyncWordBaseAddress1 as u16 + 1, 4)),
+            (PacketType::Flrc, 2) => Some((Registers::LrSyncWordBaseAddress2 as u16 + 1, 4)),
+            (PacketType::Flrc, 3) => Some((Registers::LrSyncWordBaseAddress3 as u16 + 1, 4)),
+            (PacketType::Ble, _) => Some((Registers::LrSyncWordBaseAddress1 as u16 + 1, 4)),
+            _ => None,
+        }
+    }
+
+    /// Set up to three sync words, enabling matching on each
+    ///
+    /// Sync words are written to indices 1 to 3 in order, with lengths validated against the
+    /// current packet type (see `set_syncword`). Empty entries are skipped, so `&[&a, &[], &c]`
+    /// programs sync words 1 and 3. The modem `sync_word_match` field is updated to match exactly
+    /// the programmed sync words (GFSK and FLRC modes only), use `set_syncwords_with_match` to override this.
+    pub fn set_syncwords(&mut self, words: &[&[u8]]) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.set_syncwords_with_match(words, None)
+    }
+
+    /// Set up to three sync words (as with `set_syncwords`), using the provided sync word match
+    /// configuration if set, or matching the programmed sync words otherwise
+    pub fn set_syncwords_with_match(&mut self, words: &[&[u8]], sync_match: Option<GfskSyncMatch>) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if words.len() > 3 {
+            warn!("Too many sync words provided (actual: {}, max: 3)", words.len());
+            return Err(Error::InvalidConfiguration)
+        }
+
+        let mut programmed = GfskSyncMatch::empty();
+        let flags = [GfskSyncMatch::SYNCWORD_1, GfskSyncMatch::SYNCWORD_2, GfskSyncMatch::SYNCWORD_3];
+
+        for (i, (w, f)) in words.iter().zip(flags.iter()).enumerate() {
+            if w.is_empty() {
+                continue;
+            }
+
+            self.set_syncword(i as u8 + 1, w)?;
+            programmed |= *f;
+        }
+
+        let sync_match = SyncWordRxMatch::from(sync_match.unwrap_or(programmed));
+
+        // Update sync word matching configuration
+        let mut modem = self.config.modem.clone();
+        match &mut modem {
+            Modem::Gfsk(c) => c.sync_word_match = sync_match,
+            Modem::Flrc(c) => c.sync_word_match = sync_match,
+            _ => return Ok(()),
+        }
+
+        self.configure_modem(&modem)?;
+        self.config.modem = modem;
+
+        Ok(())
+    }
+
+    /// Enable or disable packet whitening for the current GFSK, FLRC, or BLE modem configuration,
+    /// re-issuing packet parameters with only the whitening setting changed
+    ///
+    /// Returns `Error::InvalidConfiguration` for LoRa and ranging modes, which do not support whitening.
+    pub fn set_whitening(&mut self, enable: bool) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let whitening = match enable {
+            true => WhiteningModes::RADIO_WHITENING_ON,
+            false => WhiteningModes::RADIO_WHITENING_OFF,
+        };
+
+        let mut modem = self.config.modem.clone();
+        match &mut modem {
+            Modem::Gfsk(c) => c.whitening = whitening,
+            Modem::Flrc(c) => c.whitening = whitening,
+            Modem::Ble(c) => c.whitening = whitening,
+            _ => {
+                warn!("Whitening is not supported for modem: {:?}", self.config.modem);
+                return Err(Error::InvalidConfiguration)
+            },
+        }
+
+        self.configure_modem(&modem)?;
+        self.config.modem = modem;
+
+        Ok(())
+    }
+
+    /// Start receive mode with the provided RX buffer base address
+    fn start_receive_at(&mut self, base: u8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        debug!("RX start (base: {})", base);
+
+        // Set state to idle before we write configuration
+        self.set_state(State::StandbyRc)?;
+
+        let s = self.get_state()?;
+        debug!("RX setup state: {:?}", s);
+
+        // Skip buffer and modem configuration where unchanged since the last receive
+        if self.config.reconfigure_on_rx || self.rx_configured != Some(base) {
+            // Set buffer addr
+            let (tx_base, _) = self.buffer_base();
+            if let Err(e) = self.set_buff_base_addr(tx_base, base)  {
+                let s = self.get_state();
+                error!("RX error setting buffer base addr (error: {:?}, state: {:?})", e, s);
+                return Err(e);
+            }
+            
+            // Set packet mode
+            let modem_config = self.config.modem.clone();
+            
+            if let Err(e) = self.write_modem(&modem_config) {
+                let s = self.get_state();
+                error!("RX error setting configuration (error: {:?}, state: {:?})", e, s);
+                return Err(e);
+            }
+
+            self.rx_configured = Some(base);
+        } else {
+            trace!("RX configuration unchanged, skipping");
+        }
+
+        // Re-apply receiver gain mode
+        self.set_rx_boosted(self.config.rx_boosted)?;
+
+        // Configure ranging if used
+        if PacketType::Ranging == self.packet_type {
+            self.hal.write_cmd(Commands::SetRangingRole as u8, &[ RangingRole::Responder as u8 ])?;
+        }
+
+        // Setup timout
+        let config = self.rx_timeout_data();
+        
+        // Enable IRQs
+        let irqs = Self::rx_irqs();
+
+        self.set_irq_dio_mask(
+            irqs, irqs, DioMask::empty(), DioMask::empty()
+        )?;
+
+        // Enter transmit mode
+        self.hal.write_cmd(Commands::SetRx as u8, &config)?;
+        self.rx_settle_us = Some(self.config.rx_settle_us);
+        self.preamble_detected = false;
+        self.state_changed(State::Rx);
+
+        self.activity(Activity::RxStart);
+
+        let state = self.get_state()?;
+
+        debug!("RX started (state: {:?})", state);
+
+        Ok(())
+    }
+
+    /// Compute `SetRx` timeout data from the RX timeout (or shared RF timeout where unset)
+    fn rx_timeout_data(&self) -> [u8; 3] {
+        self.config.rx_timeout.as_ref().unwrap_or(&self.config.rf_timeout).data()
+    }
+
+    /// Compute `SetTx` timeout data from the TX timeout (or shared RF timeout where unset)
+    fn tx_timeout_data(&self) -> [u8; 3] {
+        self.config.tx_timeout.as_ref().unwrap_or(&self.config.rf_timeout).data()
+    }
+
+    /// Set the timeout used for transmit operations, independent of `Config.rf_timeout`
+    pub fn set_tx_timeout(&mut self, timeout: Timeout) {
+        self.config.tx_timeout = Some(timeout);
+    }
+
+    /// Set the timeout used for receive operations, independent of `Config.rf_timeout`
+    pub fn set_rx_timeout(&mut self, timeout: Timeout) {
+        self.config.rx_timeout = Some(timeout);
+    }
+
+    /// Interrupts enabled (and mapped to DIO1) in receive mode
+    fn rx_irqs() -> Irq {
+        Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT
+        | Irq::SYNCWORD_VALID | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID
+        | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED
+    }
+
+    /// Start receive mode without discarding previously received packets
+    ///
+    /// Any packet received since the last (re)start is queued and the RX buffer base address
+    /// advanced past it, so subsequent packets do not overwrite unread data. Queued packets
+    /// are read out with `drain_received`. Note the device buffer is 256 bytes and wraps,
+    /// so this should be drained before the combined queued length could exceed this.
+    pub fn start_receive_nonclearing(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.queue_received()?;
+
+        let base = self.rx_queue.next;
+        self.start_receive_at(base)
+    }
+
+    /// Fetch a received packet into `buf`, returning the packet length and information by value
+    ///
+    /// This wraps `radio::Receive::get_received` for callers without an existing `PacketInfo`.
+    pub fn receive_into(&mut self, buf: &mut [u8]) -> Result<(usize, PacketInfo), Error<CommsError, PinError, DelayError>> {
+        use radio::Receive;
+
+        let mut info = PacketInfo::default();
+        let n = self.get_received(&mut info, buf)?;
+
+        Ok((n, info))
+    }
+
+    /// Read out all queued received packets, calling `f` with the data and information for each
+    /// in order of reception, and returning the number of packets drained
+    pub fn drain_received<F: FnMut(&[u8], &PacketInfo)>(&mut self, mut f: F) -> Result<usize, Error<CommsError, PinError, DelayError>> {
+        self.queue_received()?;
+
+        let mut data = [0u8; 255];
+        let queue = core::mem::take(&mut self.rx_queue);
+
+        for ((ptr, len), info) in queue.packets.iter().zip(queue.info.iter()).take(queue.len) {
+            let d = &mut data[..*len as usize];
+
+            self.hal.read_buff_chunked(*ptr, d, self.config.max_transfer)?;
+
+            trace!("RX drained ptr: {} data: {:?} info: {:?}", ptr, d, info);
+
+            f(d, info);
+        }
+
+        Ok(queue.len)
+    }
+
+    /// Add a pending received packet (if any) to the receive queue
+    fn queue_received(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        if !self.rx_queue.pending {
+            return Ok(())
+        }
+
+        let (ptr, len) = self.rx_buffer_status()?;
+        let info = self.packet_info()?;
+
+        self.rx_queue.pending = false;
+
+        if self.rx_queue.len >= RX_QUEUE_LEN {
+            warn!("RX queue full, packet at ptr: {} will be overwritten", ptr);
+            return Ok(())
+        }
+
+        debug!("RX queued packet ptr: {} len: {}", ptr, len);
+
+        let i = self.rx_queue.len;
+        self.rx_queue.packets[i] = (ptr, len);
+        self.rx_queue.info[i] = info;
+        self.rx_queue.len += 1;
+        self.rx_queue.next = ptr.wrapping_add(len);
+
+        Ok(())
+    }
+
+    /// Apply patch for sync-word match errata in FLRC mode
+    fn patch_flrc_syncword(&mut self) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        // If we're in FLRC mode, patch to force 100% match on syncwords
+        // because otherwise the 4 bit threshold is too low
+        if let PacketType::Flrc = &self.packet_type {
+            let r = self.hal.read_reg(Registers::LrSyncWordTolerance as u16)?;
+            self.hal.write_reg(Registers::LrSyncWordTolerance as u16, r & 0xF0)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Best-effort power down on drop, see `Config::power_down_on_drop`
+impl<Hal, CommsError, PinError, DelayError> Drop for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    fn drop(&mut self) {
+        if !self.config.power_down_on_drop || self.sleep_config.is_some() {
+            return
+        }
+
+        debug!("Powering down on drop");
+
+        if let Err(e) = self.hal.sleep(SleepConfig::empty().bits()) {
+            warn!("Failed to power down on drop: {:?}", e);
+        }
+    }
+}
+
+impl<Hal, CommsError, PinError, DelayError> delay::DelayMs<u32> for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = DelayError;
+
+    fn try_delay_ms(&mut self, t: u32) -> Result<(), DelayError> {
+        self.hal.try_delay_ms(t)
+    }
+}
+
+impl<Hal, CommsError, PinError, DelayError> delay::DelayUs<u32> for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = DelayError;
+
+    fn try_delay_us(&mut self, t: u32) -> Result<(), DelayError> {
+        self.hal.try_delay_us(t)
+    }
+}
+
+/// `radio::State` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::State for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type State = State;
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Fetch device state
+    fn get_state(&mut self) -> Result<Self::State, Self::Error> {
+        let (m, _s) = self.get_status()?;
+
+        Ok(m)
+    }
+
+    /// Set device state
+    fn set_state(&mut self, state: Self::State) -> Result<(), Self::Error> {
+        // Sleep does not release BUSY so is handled separately
+        if let State::Sleep = state {
+            return self.sleep(SleepConfig::empty());
+        }
+
+        let command = match state {
+            State::Tx => Commands::SetTx,
+            State::Rx => Commands::SetRx,
+            //State::Cad => Commands::SetCad,
+            State::Fs => Commands::SetFs,
+            State::StandbyRc | State::StandbyXosc => Commands::SetStandby,
+            State::Sleep => Commands::SetSleep,
+        };
+
+        // SetStandby selects the oscillator, other commands take a zero period / config
+        let arg = match state {
+            State::StandbyXosc => 0x01,
+            _ => 0x00,
+        };
+
+        trace!("Setting state {:?} ({:x?})", state, command);
+
+        self.hal.write_cmd(command as u8, &[ arg ])?;
+
+        if let State::Rx = state {
+            self.rx_settle_us = Some(self.config.rx_settle_us);
+        }
+
+        self.state_changed(state);
+
+        Ok(())
+    }
+}
+
+/// `radio::Busy` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Busy for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Check whether the radio is busy, either transmitting or
+    /// part way through receiving a packet
+    fn is_busy(&mut self) -> Result<bool, Self::Error> {
+        match self.get_state()? {
+            State::Tx => return Ok(true),
+            State::Rx => (),
+            _ => return Ok(false),
+        }
+
+        let irq = self.get_interrupts(false)?;
+
+        if irq.intersects(Irq::PREAMBLE_DETECTED | Irq::SYNCWORD_VALID | Irq::HEADER_VALID) && 
+                !(irq.contains(Irq::RX_DONE) || irq.contains(Irq::CRC_ERROR)){
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+/// `radio::Channel` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Channel for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    /// Channel consists of an operating frequency and packet mode
+    type Channel = Channel;
+    
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Set operating channel
+    fn set_channel(&mut self, ch: &Self::Channel) -> Result<(), Self::Error> {
+        use Channel::*;
+
+        debug!("Setting channel config: {:?}", ch);
+        
+        // Set frequency
+        let freq = ch.frequency();
+        if freq < FREQ_MIN || freq > FREQ_MAX {
+            return Err(Error::InvalidFrequency)
+        }
+
+        // Check FLRC modulation parameters
+        if let Flrc(c) = ch {
+            c.validate()?;
+        }
+
+        // Refuse to change channel outside of standby or FS modes
+        self.require_state(&CONFIG_STATES)?;
+
+        self.write_channel(ch)
+    }
+}
+
+impl<Hal, CommsError, PinError, DelayError> Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    /// Write channel (frequency, packet type, and modulation) configuration
+    /// without validation or checking the device state
+    fn write_channel(&mut self, ch: &Channel) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        let freq = ch.frequency();
+        self.recalibrate_for(freq)?;
+
+        let freq_data = self.frequency_data(freq);
+
+        // Update packet type (if required)
+        let packet_type = PacketType::from(ch);
+        let packet_data = [ packet_type as u8 ];
+        
+        // Then write modulation configuration
+        let mod_data = ch.modulation_params();
+
+        // Issue commands as a single batch
+        if self.packet_type != packet_type {
+            self.write_cmds_checked(&[
+                (Commands::SetRfFrequency as u8, &freq_data),
+                (Commands::SetPacketType as u8, &packet_data),
+                (Commands::SetModulationParams as u8, &mod_data),
+            ])?;
+            self.packet_type = packet_type;
+        } else {
+            self.write_cmds_checked(&[
+                (Commands::SetRfFrequency as u8, &freq_data),
+                (Commands::SetModulationParams as u8, &mod_data),
+            ])?;
+        }
+
+        self.last_freq = Some(freq);
+
+        Ok(())
+    }
+}
+
+/// `radio::Power` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Power for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Set TX power in dBm
+    fn set_power(&mut self, power: i8) -> Result<(), Error<CommsError, PinError, DelayError>> {
+        self.set_power_checked(power).map(|_| ())
+    }
+}
+
+/// `radio::Interrupts` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Interrupts for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Irq = Irq;
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Fetch (and optionally clear) current interrupts
+    fn get_interrupts(&mut self, clear: bool) -> Result<Self::Irq, Self::Error> {
+        let mut data = [0u8; 2];
+
+        self.hal.read_cmd(Commands::GetIrqStatus as u8, &mut data)?;
+        let irq = Irq::from_bits((data[0] as u16) << 8 | data[1] as u16).unwrap();
+
+        if clear && !irq.is_empty() {
+            self.hal.write_cmd(Commands::ClearIrqStatus as u8, &data)?;
+        }
+
+        if !irq.is_empty() {
+            trace!("irq: {:?}", irq);
+        }
+
+        Ok(irq)
+    }
+}
+
+/// `radio::Transmit` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Transmit for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Start transmitting a packet
+    fn start_transmit(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let (tx_base, _) = self.buffer_base();
+        self.transmit_at(tx_base, data.len(), Some(data))
+    }
+
+    /// Check for transmit completion
+    fn check_transmit(&mut self) -> Result<bool, Self::Error> {
+        // Poll on DIO and short-circuit if not asserted
+        #[cfg(feature = "poll_irq")]
+        if self.hal.get_dio()? == PinState::Low {
+            return Ok(false)
+        }
+
+        let irq = self.get_interrupts(true)?;
+        let state = self.get_state()?;
+
+        trace!("TX poll (irq: {:?}, state: {:?})", irq, state);
+
+        if irq.contains(Irq::TX_DONE) {
+            debug!("TX complete");
+            self.activity(Activity::TxDone);
+            Ok(true)
+        } else if irq.contains(Irq::RX_TX_TIMEOUT) {
+            debug!("TX timeout");
+            Err(Error::Timeout)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+/// `radio::Receive` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Receive for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    /// Receive info structure
+    type Info = PacketInfo;
+
+    /// RF Error object
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Start radio in receive mode
+    fn start_receive(&mut self) -> Result<(), Self::Error> {
+        // Discard any queued packets
+        let (_, rx_base) = self.buffer_base();
+        self.rx_queue = RxQueue::default();
+        self.rx_queue.next = rx_base;
+
+        self.start_receive_at(rx_base)
+    }
+
+    /// Check for a received packet
+    fn check_receive(&mut self, restart: bool) -> Result<bool, Self::Error> {
+
+        // Poll on DIO and short-circuit if not asserted
+        #[cfg(feature = "poll_irq")]
+        if self.hal.get_dio()? == PinState::Low {
+            return Ok(false)
+        }
+
+        let irq = self.get_interrupts(true)?;
+        let mut res = Ok(false);
+
+        // Latch preamble detection, as the IRQ is cleared prior to any subsequent timeout
+        self.preamble_detected |= irq.contains(Irq::PREAMBLE_DETECTED);
+
+        trace!("RX poll (irq: {:?})", irq);
+       
+        // Process flags
+        if irq.contains(Irq::CRC_ERROR) {
+            debug!("RX CRC error");
+            res = Err(Error::InvalidCrc);
+        } else if irq.contains(Irq::HEADER_ERROR) {
+            debug!("RX header error");
+            res = Err(Error::InvalidHeader);
+        } else if irq.contains(Irq::RX_TX_TIMEOUT) && !self.preamble_detected {
+            debug!("RX timeout (no preamble)");
+            res = Err(Error::PreambleTimeout);
+        } else if irq.contains(Irq::RX_TX_TIMEOUT) {
+            debug!("RX timeout");
+            res = Err(Error::Timeout);
+        } else if irq.contains(Irq::SYNCWORD_ERROR) {
+            debug!("Invalid syncword");
+            res = Err(Error::InvalidSync);
+        } else if irq.contains(Irq::RX_DONE) {
+            debug!("RX complete");
+            self.rx_queue.pending = true;
+            self.activity(Activity::RxDone);
+            res = Ok(true);
+        }
+
+        // Auto-restart on failure if enabled
+        match (restart, res) {
+            (true, Err(_)) => {
+                debug!("RX restarting");
+                self.start_receive()?;
+                Ok(false)
+            },
+            (_, r) => r
+        }
+    }
+
+    /// Fetch a received packet
+    fn get_received<'a>(&mut self, info: &mut Self::Info, data: &'a mut [u8]) -> Result<usize, Self::Error> {
+        // Fetch RX buffer information
+        let (ptr, len) = self.rx_buffer_status()?;
+        self.rx_queue.pending = false;
+
+        debug!("RX get received, ptr: {} len: {}", ptr, len);
+
+        if data.len() < len as usize {
+            warn!("RX packet length {} exceeds buffer length {}", len, data.len());
+            return Err(Error::InvalidLength);
+        }
+
+        // TODO: check error packet status byte to ensure CRC is valid
+        // as this may not result in a CRC error IRQ.
+        // See chip errata for further details
+
+        // Read from the buffer at the provided pointer
+        self.hal.read_buff_chunked(ptr, &mut data[..len as usize], self.config.max_transfer)?;
+
+        // Fetch related information
+        *info = self.packet_info()?;
+
+        trace!("RX data: {:?} info: {:?}", &data[..len as usize], info);
+
+        // Return read length
+        Ok(len as usize)
+    }
+
+}
+
+/// `radio::Rssi` implementation for the SX128x
+impl<Hal, CommsError, PinError, DelayError> radio::Rssi for Sx128x<Hal, CommsError, PinError, DelayError>
+where
+    Hal: base::Hal<CommsError, PinError, DelayError>,
+    CommsError: Debug + Sync + Send + 'static,
+    PinError: Debug + Sync + Send + 'static,
+    DelayError: Debug + Sync + Send + 'static,
+{
+    type Error = Error<CommsError, PinError, DelayError>;
+
+    /// Poll for the current channel RSSI
+    /// This returns `Error::InvalidState` if the device is not in receive mode,
+    /// see `poll_rssi_unchecked` to skip this check
+    fn poll_rssi(&mut self) -> Result<i16, Error<CommsError, PinError, DelayError>> {
+        let state = self.get_state()?;
+        if state != State::Rx {
+            warn!("RSSI polled outside of receive mode (state: {:?})", state);
+            return Err(Error::InvalidState(State::Rx, state));
+        }
+
+        self.poll_rssi_unchecked()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Sx128x, Error, ValidationError, FREQ_MAX};
+    use crate::base::Hal;
+    use crate::device::*;
+    use crate::device::lora::LoRaChannel;
+
+    extern crate embedded_spi;
+    use self::driver_pal::mock::{Mock, Spi};
+
+    use radio::{State as _, Busy as _, Channel as _, Rssi as _, Interrupts as _};
+
+    pub mod vectors;
+
+    #[test]
+    fn test_hal_defaults() {
+        use std::vec::Vec;
+        use driver_pal::PinState;
+
+        // Hal implementing only the required methods, recording reset and command writes
+        #[derive(Default)]
+        struct MinimalHal {
+            resets: usize,
+            cmds: Vec<(u8, Vec<u8>)>,
+        }
+
+        impl Hal<(), (), ()> for MinimalHal {
+            fn reset(&mut self) -> Result<(), Error<(), (), ()>> { self.resets += 1; Ok(()) }
+            fn get_busy(&mut self) -> Result<PinState, Error<(), (), ()>> { Ok(PinState::Low) }
+            fn get_dio(&mut self) -> Result<PinState, Error<(), (), ()>> { Ok(PinState::Low) }
+            fn try_delay_ms(&mut self, _ms: u32) -> Result<(), ()> { Ok(()) }
+            fn try_delay_us(&mut self, _us: u32) -> Result<(), ()> { Ok(()) }
+            fn write_cmd(&mut self, command: u8, data: &[u8]) -> Result<(), Error<(), (), ()>> {
+                self.cmds.push((command, data.to_vec()));
+                Ok(())
+            }
+            fn read_cmd(&mut self, _command: u8, _data: &mut [u8]) -> Result<(), Error<(), (), ()>> { Ok(()) }
+            fn write_regs(&mut self, _reg: u16, _data: &[u8]) -> Result<(), Error<(), (), ()>> { Ok(()) }
+            fn read_regs(&mut self, _reg: u16, _data: &mut [u8]) -> Result<(), Error<(), (), ()>> { Ok(()) }
+            fn write_buff(&mut self, _offset: u8, _data: &[u8]) -> Result<(), Error<(), (), ()>> { Ok(()) }
+            fn read_buff(&mut self, _offset: u8, _data: &mut [u8]) -> Result<(), Error<(), (), ()>> { Ok(()) }
+        }
+
+        let mut hal = MinimalHal::default();
+
+        // Timed reset falls back to the untimed reset
+        hal.reset_timed(120, 80).unwrap();
+        assert_eq!(hal.resets, 1);
+
+        // Command sequences fall back to individual writes
+        hal.write_cmds(&[(0x01, &[0x02]), (0x03, &[])]).unwrap();
+        assert_eq!(hal.cmds, vec![(0x01, vec![0x02]), (0x03, vec![])]);
+
+        // Sleep and wakeup are unsupported
+        assert_eq!(hal.sleep(0), Err(Error::Unsupported));
+        assert_eq!(hal.wakeup(), Err(Error::Unsupported));
+    }
+
+    #[test]
+    fn test_api_reset() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+
+        m.expect(vectors::reset(&spi, &sdn, &delay, 50, 20));
+        radio.reset().unwrap();
+        m.finalise();
+
+        // Reset timing follows configuration
+        radio.config.reset_assert_ms = 120;
+        radio.config.reset_settle_ms = 80;
+
+        m.expect(vectors::reset(&spi, &sdn, &delay, 120, 80));
+        radio.reset().unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_status() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+
+        m.expect(vectors::status(&spi, &sdn, &delay));
+        radio.get_state().unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_firmware_version() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+
+        m.expect(vectors::firmware_version(&spi, &sdn, &delay, 16));
+        let version = radio.firmware_version().unwrap();
+        m.finalise();
+        assert_eq!(version, 16);
+    }
+
+    #[test]
+    fn test_api_power_ramp() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x1f, 0xe0));
+        radio.set_power_ramp(13, RampTime::Ramp20Us).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_sleep() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0x00));
+        radio.sleep(SleepConfig::empty()).unwrap();
+        m.finalise();
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0x01));
+        radio.sleep(SleepConfig::RETAIN_DATA_RAM).unwrap();
+        m.finalise();
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0x03));
+        radio.sleep(SleepConfig::RETAIN_DATA_RAM | SleepConfig::RETAIN_DATA_BUFFER).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_wakeup_retained() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0x01));
+        radio.sleep(SleepConfig::RETAIN_DATA_RAM).unwrap();
+        m.finalise();
+
+        // Retained configuration should not be re-written
+        m.expect(vectors::wakeup(&spi, &sdn, &delay));
+        radio.wakeup().unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_sleep_and_verify() {
+        use driver_pal::PinState;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // BUSY held high while asleep
+        let mut v = vectors::sleep(&spi, &sdn, &delay, 0x01);
+        v.push(vectors::Mt::busy(&spi, PinState::High));
+        v.append(&mut vectors::wakeup(&spi, &sdn, &delay));
+        m.expect(v);
+        radio.sleep_and_verify(SleepConfig::RETAIN_DATA_RAM).unwrap();
+        m.finalise();
+
+        // BUSY low from standby, so never slept
+        let mut v = vectors::sleep(&spi, &sdn, &delay, 0x01);
+        v.push(vectors::Mt::busy(&spi, PinState::Low));
+        m.expect(v);
+        assert_eq!(radio.sleep_and_verify(SleepConfig::RETAIN_DATA_RAM), Err(Error::InvalidState(State::Sleep, State::StandbyRc)));
+        m.finalise();
+
+        assert_eq!(radio.sleep_config, None);
+        assert_eq!(radio.last_state, State::StandbyRc);
+    }
+
+    #[test]
+    fn test_api_auto_tx() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // 1000us less the 33us offset
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetAutoTx as u8, &[0x03, 0xC7]));
+        radio.set_auto_tx(AutoTx::Enabled(1000)).unwrap();
+        m.finalise();
+
+        // Timeouts below the offset would underflow and are rejected before any SPI
+        m.expect(vectors::none());
+        assert_eq!(radio.set_auto_tx(AutoTx::Enabled(AUTO_RX_TX_OFFSET - 1)), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_auto_fs() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetAutoFs as u8, &[0x01]));
+        radio.set_auto_fs(true).unwrap();
+        m.finalise();
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetAutoFs as u8, &[0x00]));
+        radio.set_auto_fs(false).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_busy() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        assert!(radio.is_busy().unwrap());
+        m.finalise();
+
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        assert!(!radio.is_busy().unwrap());
+        m.finalise();
+
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::Rx);
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED | Irq::HEADER_VALID));
+        m.expect(v);
+        assert!(radio.is_busy().unwrap());
+        m.finalise();
+
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::Rx);
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        m.expect(v);
+        assert!(!radio.is_busy().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_hop_to() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let freqs = [2_402_000_000, 2_440_000_000, 2_480_000_000];
+        let table = HopTable::new(&radio.config, &freqs).unwrap();
+
+        for (i, f) in freqs.iter().enumerate() {
+            let c = radio.config.freq_to_steps_u64(*f);
+            let data = [(c >> 16) as u8, (c >> 8) as u8, c as u8];
+
+            // Hop output should match set_frequency for the same frequency,
+            // with set_frequency recalibrating on each (large) change after the first
+            let mut v = match i {
+                0 => vectors::none(),
+                _ => {
+                    let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+                    v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::Calibrate as u8, &[0x3C]));
+                    v
+                },
+            };
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &data));
+            m.expect(v);
+            radio.set_frequency(*f).unwrap();
+            m.finalise();
+
+            m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &data));
+            radio.hop_to(&table, i).unwrap();
+            m.finalise();
+        }
+
+        // Large hops recalibrate
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::Calibrate as u8, &[0x3C]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, table.get(0).unwrap()));
+        m.expect(v);
+        radio.hop_to(&table, 0).unwrap();
+        m.finalise();
+
+        // Out of range indices are rejected
+        m.expect(vectors::none());
+        assert_eq!(radio.hop_to(&table, freqs.len()), Err(Error::InvalidConfiguration));
+        m.finalise();
+
+        // As are out of band frequencies
+        assert_eq!(HopTable::new(&radio.config, &[2_440_000_000, FREQ_MAX + 1_000_000]), Err(ValidationError::InvalidFrequency));
+    }
+
+    #[test]
+    #[cfg(feature = "float")]
+    fn test_api_set_frequency_compensated() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let f = 2_440_000_000;
+        let nominal = radio.config.freq_to_steps_u64(f);
+
+        // +25 C at +1 ppm/C should lower the programmed frequency by 25 ppm
+        let expected = radio.config.freq_to_steps_u64(f - 61_000);
+        assert!(expected < nominal);
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8,
+            &[(expected >> 16) as u8, (expected >> 8) as u8, expected as u8]));
+        radio.set_frequency_compensated(f, 1.0, 25, || Some(50)).unwrap();
+        m.finalise();
+
+        // Failed temperature reads fall back to the nominal frequency
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8,
+            &[(nominal >> 16) as u8, (nominal >> 8) as u8, nominal as u8]));
+        radio.set_frequency_compensated(f, 1.0, 25, || None).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_channel_index() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Channel 10 at 2 MHz spacing from 2.402 GHz
+        let steps = radio.config.freq_to_steps_u64(2_422_000_000);
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8,
+            &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+        radio.set_channel_index(2_402_000_000, 2_000_000, 10).unwrap();
+        m.finalise();
+
+        // Channel 60 lands above the band
+        m.expect(vectors::none());
+        assert_eq!(radio.set_channel_index(2_402_000_000, 2_000_000, 60), Err(Error::InvalidFrequency));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_frequency_bounds() {
+        let mut m = Mock::new();
+        let (spi, _sdn, _busy, _delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Below band frequencies are rejected before any SPI write
+        m.expect(vectors::none());
+        assert_eq!(radio.set_frequency(2_300_000_000), Err(Error::InvalidFrequency));
+        m.finalise();
+
+        // As are above band frequencies
+        m.expect(vectors::none());
+        assert_eq!(radio.set_frequency(2_600_000_000), Err(Error::InvalidFrequency));
+        m.finalise();
+
+        assert_eq!(radio.last_freq, None);
+    }
+
+    #[test]
+    fn test_api_buff_chunked() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let mut data = [0u8; 40];
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = i as u8;
+        }
+
+        // Writes are split into transfers of at most 16 bytes
+        let mut v = vectors::write_buff(&spi, &sdn, &delay, 0, &data[..16]);
+        v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 16, &data[16..32]));
+        v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 32, &data[32..]));
+        m.expect(v);
+        radio.hal.write_buff_chunked(0, &data, 16).unwrap();
+        m.finalise();
+
+        // As are reads, with offsets wrapping around the buffer
+        let mut v = vectors::read_buff(&spi, &sdn, &delay, 240, &data[..16]);
+        v.append(&mut vectors::read_buff(&spi, &sdn, &delay, 0, &data[16..32]));
+        v.append(&mut vectors::read_buff(&spi, &sdn, &delay, 16, &data[32..]));
+        m.expect(v);
+        let mut buff = [0u8; 40];
+        radio.hal.read_buff_chunked(240, &mut buff, 16).unwrap();
+        assert_eq!(&buff[..], &data[..]);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_rx_buffer_status() {
+        use crate::device::lora::LoRaHeader;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Explicit header length is read from buffer status
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[12, 34]));
+        assert_eq!(radio.rx_buffer_status().unwrap(), (34, 12));
+        m.finalise();
+
+        // Implicit header length is read from the payload length register
+        if let Modem::LoRa(c) = &mut radio.config.modem {
+            c.header_type = LoRaHeader::Implicit;
+        }
+
+        let mut v = vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[0, 34]);
+        v.append(&mut vectors::read_reg(&spi, &sdn, &delay, Registers::LrPayloadLength as u16, 20));
+        m.expect(v);
+        assert_eq!(radio.rx_buffer_status().unwrap(), (34, 20));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_packet_info() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[120, 0xF8, 0, 0, 0x02]));
+        let info = radio.packet_info().unwrap();
+        assert_eq!(info.rssi, -60);
+        assert_eq!(info.snr, Some(-2));
+        assert_eq!(info.sync_addr_status, 0x02);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_last_packet_metrics() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // LoRa reports RSSI and SNR
+        radio.packet_type = PacketType::LoRa;
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[120, 0xF8, 0, 0, 0x02]));
+        assert_eq!(radio.last_packet_metrics().unwrap(), (-60, Some(-2)));
+        m.finalise();
+
+        // GFSK reports RSSI only
+        radio.packet_type = PacketType::Gfsk;
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[0, 150, 0, 0, 0x01]));
+        assert_eq!(radio.last_packet_metrics().unwrap(), (-75, None));
+        m.finalise();
+
+        // Unconfigured packet type is rejected
+        radio.packet_type = PacketType::None;
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[0, 150, 0, 0, 0x01]));
+        assert_eq!(radio.last_packet_metrics(), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_lna_gain() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Manual gain enables manual control, disables demod detection, and sets the gain value
+        let mut v = vectors::update_reg(&spi, &sdn, &delay, Registers::EnableManuaLGainControl as u16, 0x01, 0x81);
+        v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::DemodDetection as u16, 0x31, 0x30));
+        v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::ManualGainValue as u16, 0xA1, 0xAB));
+        m.expect(v);
+        radio.set_lna_gain(GainMode::Manual(11)).unwrap();
+        m.finalise();
+
+        // Auto gain restores AGC
+        let mut v = vectors::update_reg(&spi, &sdn, &delay, Registers::EnableManuaLGainControl as u16, 0x81, 0x01);
+        v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::DemodDetection as u16, 0x30, 0x31));
+        m.expect(v);
+        radio.set_lna_gain(GainMode::Auto).unwrap();
+        m.finalise();
+
+        // Out of range gains are rejected
+        m.expect(vectors::none());
+        assert_eq!(radio.set_lna_gain(GainMode::Manual(MANUAL_GAIN_MAX + 1)), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_rx_boosted() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Enabling sets LNA regime bits, preserving others
+        m.expect(vectors::update_reg(&spi, &sdn, &delay, Registers::LnaRegime as u16, 0x15, 0xD5));
+        radio.set_rx_boosted(true).unwrap();
+        assert!(radio.config.rx_boosted);
+        m.finalise();
+
+        // Disabling clears them
+        m.expect(vectors::update_reg(&spi, &sdn, &delay, Registers::LnaRegime as u16, 0xD5, 0x15));
+        radio.set_rx_boosted(false).unwrap();
+        assert!(!radio.config.rx_boosted);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_context() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let regs: [(u16, &[u8]); 11] = [
+            (Registers::LrSyncWordBaseAddress1 as u16, &[0x01, 0x02, 0x03, 0x04, 0x05]),
+            (Registers::LrSyncWordBaseAddress2 as u16, &[0x11, 0x12, 0x13, 0x14, 0x15]),
+            (Registers::LrSyncWordBaseAddress3 as u16, &[0x21, 0x22, 0x23, 0x24, 0x25]),
+            (Registers::LrSyncWordTolerance as u16, &[0x03]),
+            (Registers::LrCrcSeedBaseAddr as u16, &[0x1D, 0x0F]),
+            (Registers::LrCrcPolyBaseAddr as u16, &[0x10, 0x21]),
+            (Registers::LrWhitSeedBaseAddr as u16, &[0x01]),
+            (Registers::LnaRegime as u16, &[0xC0]),
+            (Registers::EnableManuaLGainControl as u16, &[0x80]),
+            (Registers::DemodDetection as u16, &[0x30]),
+            (Registers::ManualGainValue as u16, &[0x0B]),
+        ];
+
+        // Save reads each register
+        let mut v = vectors::none();
+        for (reg, data) in regs.iter() {
+            v.append(&mut vectors::read_regs(&spi, &sdn, &delay, *reg, data));
+        }
+        m.expect(v);
+        let ctx = radio.save_context().unwrap();
+        m.finalise();
+
+        // Restore writes configuration then the identical register set
+        let config = &ctx.config;
+        let channel = match &config.channel {
+            Channel::LoRa(c) => c.clone(),
+            _ => unreachable!(),
+        };
+        let modem = match &config.modem {
+            Modem::LoRa(c) => c.clone(),
+            _ => unreachable!(),
+        };
+        let steps = config.freq_to_steps_u64(channel.freq);
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRegulatorMode as u8, &[config.regulator_mode as u8]);
+        v.append(&mut vectors::set_channel(&spi, &sdn, &delay, steps, Some(PacketType::LoRa as u8), &[channel.sf as u8, channel.bw as u8, channel.cr as u8]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            modem.preamble_length as u8, modem.header_type as u8, modem.payload_length as u8, modem.crc_mode as u8, modem.invert_iq as u8, 0, 0,
+        ]));
+        v.append(&mut vectors::set_power_ramp(&spi, &sdn, &delay, (config.pa_config.power + 18) as u8, config.pa_config.ramp_time as u8));
+        for (reg, data) in regs.iter() {
+            v.append(&mut vectors::write_regs(&spi, &sdn, &delay, *reg, data));
+        }
+        m.expect(v);
+        radio.restore_context(&ctx).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config, ctx.config);
+    }
+
+    #[test]
+    fn test_api_poll_rssi() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Checked polling reads RSSI in receive mode
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::Rx);
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[180]));
+        m.expect(v);
+        assert_eq!(radio.poll_rssi().unwrap(), -90);
+        m.finalise();
+
+        // And is rejected outside of receive mode
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        assert_eq!(radio.poll_rssi(), Err(Error::InvalidState(State::Rx, State::StandbyRc)));
+        m.finalise();
+
+        // Unchecked polling does not check state
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[180]));
+        assert_eq!(radio.poll_rssi_unchecked().unwrap(), -90);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_power_checked() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        let ramp = radio.config.pa_config.ramp_time as u8;
+
+        // In range power is applied directly
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x1C, ramp));
+        assert_eq!(radio.set_power_checked(10).unwrap(), 10);
+        m.finalise();
+
+        // Out of range power is clamped by default
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x1F, ramp));
+        assert_eq!(radio.set_power_checked(20).unwrap(), TX_POWER_MAX);
+        m.finalise();
+
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x00, ramp));
+        assert_eq!(radio.set_power_checked(-30).unwrap(), TX_POWER_MIN);
+        m.finalise();
+
+        // And rejected in strict mode
+        radio.config.strict_power = true;
+        m.expect(vectors::none());
+        assert_eq!(radio.set_power_checked(20), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_ll() {
+        use crate::ll;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, ll::Commands::SetAutoFs as u8, &[0x01]);
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, ll::Commands::GetRssiInst as u8, &[0x40]));
+        v.append(&mut vectors::write_regs(&spi, &sdn, &delay, ll::Registers::LrSyncWordTolerance as u16, &[0x02]));
+        v.append(&mut vectors::read_regs(&spi, &sdn, &delay, ll::Registers::LrCrcSeedBaseAddr as u16, &[0x12, 0x34]));
+        m.expect(v);
+
+        radio.write_command(ll::Commands::SetAutoFs as u8, &[0x01]).unwrap();
+
+        let mut rssi = [0u8; 1];
+        radio.read_command(ll::Commands::GetRssiInst as u8, &mut rssi).unwrap();
+        assert_eq!(rssi, [0x40]);
+
+        radio.write_register(ll::Registers::LrSyncWordTolerance as u16, &[0x02]).unwrap();
+
+        let mut seed = [0u8; 2];
+        radio.read_register(ll::Registers::LrCrcSeedBaseAddr as u16, &mut seed).unwrap();
+        assert_eq!(seed, [0x12, 0x34]);
+
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_syncwords() {
+        use crate::device::common::SyncWordRxMatch::{self, *};
+
+        let words: [&[u8]; 3] = [
+            &[0x11, 0x12, 0x13, 0x14, 0x15],
+            &[0x21, 0x22, 0x23, 0x24, 0x25],
+            &[0x31, 0x32, 0x33, 0x34, 0x35],
+        ];
+        let addrs = [
+            Registers::LrSyncWordBaseAddress1 as u16,
+            Registers::LrSyncWordBaseAddress2 as u16,
+            Registers::LrSyncWordBaseAddress3 as u16,
+        ];
+        let matches = [RADIO_RX_MATCH_SYNCWORD_1, RADIO_RX_MATCH_SYNCWORD_1_2, RADIO_RX_MATCH_SYNCWORD_1_2_3];
+
+        for n in 1..=3 {
+            let mut m = Mock::new();
+            let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+            let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+            radio.config = Config::gfsk();
+            radio.packet_type = PacketType::Gfsk;
+
+            let c = match &radio.config.modem {
+                Modem::Gfsk(c) => c.clone(),
+                _ => unreachable!(),
+            };
+            let sync_match: SyncWordRxMatch = matches[n - 1];
+
+            let mut v = vectors::none();
+            for (addr, word) in addrs.iter().zip(words.iter()).take(n) {
+                v.append(&mut vectors::write_regs(&spi, &sdn, &delay, *addr, word));
+            }
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+                c.preamble_length as u8, c.sync_word_length as u8, sync_match as u8, c.header_type as u8,
+                c.payload_length, c.crc_mode as u8, c.whitening as u8,
+            ]));
+            m.expect(v);
+
+            radio.set_syncwords(&words[..n]).unwrap();
+            m.finalise();
+
+            match &radio.config.modem {
+                Modem::Gfsk(c) => assert_eq!(c.sync_word_match, sync_match),
+                _ => unreachable!(),
+            }
+        }
+
+        // More than three sync words are rejected
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::Gfsk;
+
+        m.expect(vectors::none());
+        assert_eq!(radio.set_syncwords(&[words[0], words[1], words[2], words[0]]), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_syncwords_sparse() {
+        use crate::device::common::SyncWordRxMatch;
+        use crate::device::gfsk::GfskSyncMatch;
+
+        let w1 = [0x11, 0x12, 0x13, 0x14, 0x15];
+        let w3 = [0x31, 0x32, 0x33, 0x34, 0x35];
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.config = Config::gfsk();
+        radio.packet_type = PacketType::Gfsk;
+
+        let c = match &radio.config.modem {
+            Modem::Gfsk(c) => c.clone(),
+            _ => unreachable!(),
+        };
+
+        // Programming words 1 and 3 matches on only words 1 and 3
+        let sync_match = SyncWordRxMatch::from(GfskSyncMatch::SYNCWORD_1 | GfskSyncMatch::SYNCWORD_3);
+        assert_eq!(sync_match, SyncWordRxMatch::RADIO_RX_MATCH_SYNCWORD_1_3);
+
+        let mut v = vectors::write_regs(&spi, &sdn, &delay, Registers::LrSyncWordBaseAddress1 as u16, &w1);
+        v.append(&mut vectors::write_regs(&spi, &sdn, &delay, Registers::LrSyncWordBaseAddress3 as u16, &w3));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.sync_word_length as u8, sync_match as u8, c.header_type as u8,
+            c.payload_length, c.crc_mode as u8, c.whitening as u8,
+        ]));
+        m.expect(v);
+
+        radio.set_syncwords(&[&w1, &[], &w3]).unwrap();
+        m.finalise();
+
+        match &radio.config.modem {
+            Modem::Gfsk(c) => assert_eq!(c.sync_word_match, sync_match),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_api_verify_packet_type() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketType as u8, &[PacketType::LoRa as u8]));
+        radio.verify_packet_type().unwrap();
+        m.finalise();
+
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketType as u8, &[PacketType::Gfsk as u8]));
+        assert_eq!(radio.verify_packet_type(), Err(Error::InvalidResponse(PacketType::Gfsk as u8)));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_drain_received() {
+        use std::vec::Vec;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let packets: [(u8, &[u8], u8); 2] = [
+            (0, &[0x01, 0x02, 0x03, 0x04], 100),
+            (4, &[0x11, 0x12, 0x13, 0x14, 0x15, 0x16], 120),
+        ];
+
+        // Queue the first packet on restart
+        radio.rx_queue.pending = true;
+        let mut v = vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[packets[0].1.len() as u8, packets[0].0]);
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[packets[0].2, 0, 0, 0, 0]));
+        m.expect(v);
+        radio.queue_received().unwrap();
+        m.finalise();
+
+        assert_eq!(radio.rx_queue.next, 4);
+
+        // Then the second on drain, reading out both in order
+        radio.rx_queue.pending = true;
+        let mut v = vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[packets[1].1.len() as u8, packets[1].0]);
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[packets[1].2, 0, 0, 0, 0]));
+        for (ptr, data, _) in packets.iter() {
+            v.append(&mut vectors::read_buff(&spi, &sdn, &delay, *ptr, data));
+        }
+        m.expect(v);
+
+        let mut received = Vec::new();
+        let n = radio.drain_received(|d, i| received.push((d.to_vec(), i.rssi)) ).unwrap();
+        m.finalise();
+
+        assert_eq!(n, 2);
+        assert_eq!(received, vec![
+            (packets[0].1.to_vec(), -50),
+            (packets[1].1.to_vec(), -60),
+        ]);
+        assert_eq!(radio.rx_queue.len, 0);
+    }
+
+    #[test]
+    fn test_api_poll_rssi_averaged() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let raw = [160u8, 180, 200, 140];
+
+        let expect = |m: &mut Mock| {
+            let mut v = vectors::get_state(&spi, &sdn, &delay, State::Rx);
+            for (i, r) in raw.iter().enumerate() {
+                if i != 0 {
+                    v.push(vectors::Mt::delay_us(500));
+                }
+                v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[*r]));
+            }
+            m.expect(v);
+        };
+
+        // Samples (-80, -90, -100, -70) are averaged
+        expect(&mut m);
+        assert_eq!(radio.poll_rssi_averaged(raw.len() as u8, 500).unwrap(), -85);
+        m.finalise();
+
+        expect(&mut m);
+        assert_eq!(radio.poll_rssi_stats(raw.len() as u8, 500).unwrap(), RssiStats{ mean: -85, min: -100, max: -70 });
+        m.finalise();
+
+        // RX mode is required
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        assert_eq!(radio.poll_rssi_averaged(raw.len() as u8, 500), Err(Error::InvalidState(State::Rx, State::StandbyRc)));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_new_calibration() {
+        for skip in [false, true].iter() {
+            let mut m = Mock::new();
+            let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+            let config = Config { skip_calibration: *skip, ..Default::default() };
+
+            // Calibration is issued prior to configuration unless skipped
+            let v = vectors::new_sequence(&spi, &sdn, &delay, &config);
+            let calibrate = vectors::Mt::spi_write(&spi, &[Commands::Calibrate as u8], &[CalibrationParams::all().bits()]);
+            assert_eq!(v.contains(&calibrate), !skip);
+            m.expect(v);
+
+            let _radio = Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).unwrap();
+            m.finalise();
+        }
+    }
+
+    #[test]
+    fn test_api_new_firmware_versions() {
+        let mut config = Config {
+            skip_calibration: true,
+            accepted_firmware_versions: &[0xA9B5, 0xB7A9],
+            ..Default::default()
+        };
+
+        // Listed versions other than the default are accepted
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xB7A9));
+        v.append(&mut vectors::init_sequence(&spi, &sdn, &delay, &config));
+        m.expect(v);
+
+        let radio = Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config.accepted_firmware_versions, &[0xA9B5, 0xB7A9]);
+
+        // Unlisted versions are rejected
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0x1234));
+        m.expect(v);
+
+        assert_eq!(Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).err(), Some(Error::InvalidDevice(0x1234)));
+        m.finalise();
+
+        // And missing devices always reported as such
+        config.accepted_firmware_versions = &[0xFFFF];
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xFFFF));
+        v.append(&mut vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms * 2));
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xFFFF));
+        m.expect(v);
+
+        assert_eq!(Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).err(), Some(Error::NoComms));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_new_version_mismatch_policy() {
+        for policy in [VersionMismatchPolicy::Error, VersionMismatchPolicy::Warn, VersionMismatchPolicy::Ignore].iter() {
+            let config = Config {
+                skip_calibration: true,
+                version_mismatch_policy: *policy,
+                ..Default::default()
+            };
+
+            let mut m = Mock::new();
+            let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+            let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+            v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xB7A9));
+
+            // Only the error policy stops initialisation
+            if *policy != VersionMismatchPolicy::Error {
+                v.append(&mut vectors::init_sequence(&spi, &sdn, &delay, &config));
+            }
+            m.expect(v);
+
+            let res = Sx128x::<Spi, _, _, _>::new(spi.clone(), &config);
+            m.finalise();
+
+            match policy {
+                VersionMismatchPolicy::Error => assert_eq!(res.err(), Some(Error::InvalidDevice(0xB7A9))),
+                _ => assert!(res.is_ok()),
+            }
+        }
+
+        // The deprecated skip flag maps to the ignore policy
+        #[allow(deprecated)]
+        let config = Config {
+            skip_calibration: true,
+            skip_version_check: true,
+            ..Default::default()
+        };
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xB7A9));
+        v.append(&mut vectors::init_sequence(&spi, &sdn, &delay, &config));
+        m.expect(v);
+
+        assert!(Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).is_ok());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_new_reset_retries() {
+        let mut config = Config { skip_calibration: true, ..Default::default() };
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        // No response on first reset, device detected following retry
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0x0000));
+        v.append(&mut vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms * 2));
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0xA9B5));
+        v.append(&mut vectors::init_sequence(&spi, &sdn, &delay, &config));
+        m.expect(v);
+
+        let _radio = Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).unwrap();
+        m.finalise();
+
+        // Retries disabled
+        config.reset_retries = 0;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        let mut v = vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms);
+        v.append(&mut vectors::firmware_version(&spi, &sdn, &delay, 0x0000));
+        m.expect(v);
+
+        assert_eq!(Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).err(), Some(Error::NoComms));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_recalibrate_on_frequency_change() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let freq_cmd = |f: u32| {
+            let c = Config::default().freq_to_steps_u64(f);
+            vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &[(c >> 16) as u8, (c >> 8) as u8, c as u8])
+        };
+
+        // Initial and small frequency changes do not recalibrate
+        let mut v = freq_cmd(2_402_000_000);
+        v.append(&mut freq_cmd(2_410_000_000));
+        m.expect(v);
+        radio.set_frequency(2_402_000_000).unwrap();
+        radio.set_frequency(2_410_000_000).unwrap();
+        m.finalise();
+
+        // Large changes recalibrate ADC and PLL
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::Calibrate as u8, &[0x3C]));
+        v.append(&mut freq_cmd(2_480_000_000));
+        m.expect(v);
+        radio.set_frequency(2_480_000_000).unwrap();
+        m.finalise();
+
+        // Calibration is run from STDBY_RC, restoring the prior state
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyXosc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0x00]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::Calibrate as u8, &[0x3C]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0x01]));
+        v.append(&mut freq_cmd(2_402_000_000));
+        m.expect(v);
+        radio.set_frequency(2_402_000_000).unwrap();
+        m.finalise();
+
+        // And skipped outside of standby and FS
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::Rx);
+        v.append(&mut freq_cmd(2_480_000_000));
+        m.expect(v);
+        radio.set_frequency(2_480_000_000).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    #[cfg(feature = "float")]
+    fn test_link_budget() {
+        use crate::device::lora::LoRaSpreadingFactor;
+
+        // LoRa packets include demodulation margin
+        let info = PacketInfo{ rssi: -90, snr: Some(5), ..Default::default() };
+        assert_eq!(info.link_budget(10, Some(LoRaSpreadingFactor::Sf7)), LinkBudget{ path_loss: 100, snr_margin: Some(12.5) });
+
+        // GFSK packets have no SNR
+        let info = PacketInfo{ rssi: -75, snr: None, ..Default::default() };
+        assert_eq!(info.link_budget(-5, None), LinkBudget{ path_loss: 70, snr_margin: None });
+    }
+
+    #[test]
+    fn test_flrc_validate() {
+        use crate::device::flrc::*;
+
+        let valid = FlrcChannel{ br_bw: FlrcBitrateBandwidth::Br2600Bw2400, cr: FlrcCodingRate::Cr3_4, ..Default::default() };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let invalid = FlrcChannel{ br_bw: FlrcBitrateBandwidth::Br2600Bw2400, cr: FlrcCodingRate::Cr1_2, ..Default::default() };
+        assert_eq!(invalid.validate(), Err(ValidationError::InvalidConfiguration));
+
+        // Invalid channels are rejected prior to configuration
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::none());
+        assert_eq!(radio.set_channel(&Channel::Flrc(invalid)), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_enter_fs() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // PLL locks after a single poll interval
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetFs as u8, &[0]);
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyXosc, CommandStatus::Success));
+        v.push(vectors::Mt::delay_ms(1));
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::Fs, CommandStatus::Success));
+        m.expect(v);
+
+        radio.enter_fs().unwrap();
+        m.finalise();
+
+        // PLL fails to lock within the timeout
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetFs as u8, &[0]);
+        for _ in 0..FS_LOCK_TIMEOUT_MS {
+            v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyXosc, CommandStatus::Success));
+            v.push(vectors::Mt::delay_ms(1));
+        }
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyXosc, CommandStatus::Success));
+        m.expect(v);
+
+        assert_eq!(radio.enter_fs(), Err(Error::InvalidState(State::Fs, State::StandbyXosc)));
+        m.finalise();
+
+        // Command execution failure
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetFs as u8, &[0]);
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::ExecutionFailure));
+        m.expect(v);
+
+        assert_eq!(radio.enter_fs(), Err(Error::InvalidState(State::Fs, State::StandbyRc)));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_rssi_threshold() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        assert_eq!(rssi_to_raw(-90), Some(180));
+        assert_eq!(rssi_to_raw(10), None);
+
+        // Not supported in LoRa mode
+        radio.config = Config::lora();
+        assert_eq!(radio.set_rssi_threshold(-90), Err(Error::InvalidConfiguration));
+
+        radio.config = Config::gfsk();
+        assert_eq!(radio.carrier_detect(), Err(Error::InvalidConfiguration));
+        radio.set_rssi_threshold(-90).unwrap();
+        assert_eq!(radio.rssi_threshold, Some(180));
+
+        // -85 dBm is above the threshold
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[170]));
+        assert!(radio.carrier_detect().unwrap());
+        m.finalise();
+
+        // -95 dBm is below the threshold
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[190]));
+        assert!(!radio.carrier_detect().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_wait_irq() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // IRQ asserts on the third poll, only matched bits are cleared
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::empty());
+        v.push(vectors::Mt::delay_ms(1));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED));
+        v.push(vectors::Mt::delay_ms(1));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED | Irq::RX_DONE));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x02]));
+        m.expect(v);
+        assert_eq!(radio.wait_irq(Irq::RX_DONE | Irq::CRC_ERROR, 10).unwrap(), Irq::RX_DONE);
+        m.finalise();
+
+        // IRQ never asserts
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::empty());
+        v.push(vectors::Mt::delay_ms(1));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        v.push(vectors::Mt::delay_ms(1));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED));
+        m.expect(v);
+        assert_eq!(radio.wait_irq(Irq::RX_DONE, 2), Err(Error::Timeout));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_clear_irq() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Clearing preamble detection leaves RX done pending
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x80, 0x00]);
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::RX_DONE));
+        m.expect(v);
+
+        radio.clear_irq(Irq::PREAMBLE_DETECTED).unwrap();
+        assert_eq!(radio.get_interrupts(false).unwrap(), Irq::RX_DONE);
+        m.finalise();
+
+        // Multi-byte masks are encoded big-endian
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x40, 0x42]));
+        radio.clear_irq(Irq::RX_TX_TIMEOUT | Irq::CRC_ERROR | Irq::RX_DONE).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_config_validate() {
+        use crate::device::flrc::*;
+        use crate::device::common::SyncWordRxMatch;
+
+
+        assert_eq!(Config::default().validate(), Ok(()));
+        assert_eq!(Config::gfsk().validate(), Ok(()));
+        assert_eq!(Config::flrc().validate(), Ok(()));
+
+        // Mismatched modem and channel
+        let c = Config { channel: Channel::Flrc(FlrcChannel::default()), ..Default::default() };
+        assert_eq!(c.validate(), Err(ValidationError::InvalidConfiguration));
+
+        // Frequency out of range
+        let c = Config { channel: Channel::LoRa(LoRaChannel{ freq: FREQ_MAX + 1, ..Default::default() }), ..Default::default() };
+        assert_eq!(c.validate(), Err(ValidationError::InvalidFrequency));
+
+        // Power out of range (strict only)
+        let mut c = Config::default();
+        c.pa_config.power = TX_POWER_MAX + 1;
+        assert_eq!(c.validate(), Ok(()));
+        c.strict_power = true;
+        assert_eq!(c.validate(), Err(ValidationError::InvalidConfiguration));
+
+        // FLRC sync word matching without a sync word
+        let mut c = Config::flrc();
+        c.modem = Modem::Flrc(FlrcConfig{ sync_word_length: FlrcSyncWordLength::None, sync_word_match: SyncWordRxMatch::RADIO_RX_MATCH_SYNCWORD_1, ..Default::default() });
+        assert_eq!(c.validate(), Err(ValidationError::InvalidConfiguration));
+
+        // Invalid configurations are rejected without bus access
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::none());
+        assert_eq!(radio.configure(&c), Err(Error::InvalidConfiguration));
+        assert!(Sx128x::<Spi, _, _, _>::new(spi.clone(), &c).is_err());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_write_cmd_checked() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let cmd = Commands::SetPacketType as u8;
+        let data = [PacketType::LoRa as u8];
+
+        // First attempt fails, retry succeeds
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, cmd, &data);
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::ProcessingError));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, cmd, &data));
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::Success));
+        m.expect(v);
+        radio.write_cmd_checked(cmd, &data).unwrap();
+        m.finalise();
+
+        // Retries exhausted
+        radio.config.command_retries = 1;
+
+        let mut v = vectors::none();
+        for _ in 0..2 {
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, cmd, &data));
+            v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::ExecutionFailure));
+        }
+        m.expect(v);
+        assert_eq!(radio.write_cmd_checked(cmd, &data), Err(Error::InvalidResponse(CommandStatus::ExecutionFailure as u8)));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_ranging_calibration() {
+        use crate::device::lora::*;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // SF9 / BW400 selects the appropriate table value
+        radio.config.channel = Channel::Ranging(LoRaChannel{ sf: LoRaSpreadingFactor::Sf9, bw: LoRaBandwidth::Bw400kHz, ..Default::default() });
+
+        m.expect(vectors::write_regs(&spi, &sdn, &delay, Registers::LrRangingReRxTxDelayCal as u16, &10230u16.to_be_bytes()));
+        radio.set_ranging_calibration().unwrap();
+        m.finalise();
+
+        // Raw values override the table
+        m.expect(vectors::write_regs(&spi, &sdn, &delay, Registers::LrRangingReRxTxDelayCal as u16, &[0x12, 0x34]));
+        radio.set_ranging_calibration_raw(0x1234).unwrap();
+        m.finalise();
+
+        // Unsupported bandwidths are rejected
+        radio.config.channel = Channel::Ranging(LoRaChannel{ sf: LoRaSpreadingFactor::Sf9, bw: LoRaBandwidth::Bw200kHz, ..Default::default() });
+
+        m.expect(vectors::none());
+        assert_eq!(radio.set_ranging_calibration(), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_is_receiving() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Idle RX
+        m.expect(vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        assert!(!radio.is_receiving().unwrap());
+        m.finalise();
+
+        // Mid-reception, IRQs are not cleared
+        m.expect(vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED | Irq::HEADER_VALID));
+        assert!(radio.is_receiving().unwrap());
+        m.finalise();
+
+        // Reception complete
+        m.expect(vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED | Irq::HEADER_VALID | Irq::RX_DONE));
+        assert!(!radio.is_receiving().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_check_receive_irq() {
+        use driver_pal::PinState;
+
+        let mut m = Mock::new();
+        let (spi, _sdn, _busy, _delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Low DIO1 short-circuits prior to any SPI transaction
+        m.expect(&[
+            vectors::Mt::ready(&spi, PinState::Low),
+        ]);
+        assert!(!radio.check_receive_irq().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_polarity() {
+        use driver_pal::PinState;
+        use vectors::Mt;
+        use crate::base::Polarity;
+
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Polarity<Spi>, _, _, _>::build(Polarity::new(spi.clone(), true, true));
+
+        // Active low BUSY is awaited until driven high
+        m.expect(vec![
+            Mt::busy(&spi, PinState::Low),
+            Mt::delay_ms(1),
+            Mt::busy(&spi, PinState::High),
+            Mt::spi_write(&spi, &[Commands::SetStandby as u8], &[0x00]),
+            Mt::busy(&spi, PinState::High),
+        ]);
+        radio.set_state(State::StandbyRc).unwrap();
+        m.finalise();
+
+        // Active low DIO is not asserted when high
+        m.expect(vec![
+            Mt::ready(&spi, PinState::High),
+        ]);
+        assert!(!radio.check_receive_irq().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_poll_receive() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let events = [
+            (Irq::PREAMBLE_DETECTED, RxEvent::PreambleDetected, [0x80, 0x00]),
+            (Irq::HEADER_VALID, RxEvent::HeaderValid, [0x00, 0x10]),
+            (Irq::RX_DONE, RxEvent::Done, [0x00, 0x02]),
+            (Irq::CRC_ERROR | Irq::RX_DONE, RxEvent::CrcError, [0x00, 0x42]),
+            (Irq::HEADER_ERROR, RxEvent::HeaderError, [0x00, 0x20]),
+            (Irq::RX_TX_TIMEOUT, RxEvent::PreambleTimeout, [0x40, 0x00]),
+            (Irq::RX_TX_TIMEOUT | Irq::PREAMBLE_DETECTED, RxEvent::Timeout, [0xC0, 0x00]),
+        ];
+
+        // Each event is returned and only the matching IRQ cleared
+        for (irq, event, clear) in events.iter() {
+            radio.preamble_detected = false;
+
+            let mut v = vectors::get_irq(&spi, &sdn, &delay, *irq);
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, clear));
+            m.expect(v);
+            assert_eq!(radio.poll_receive().unwrap(), Some(*event));
+            m.finalise();
+        }
+
+        // Higher priority events are returned first
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED | Irq::HEADER_VALID);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x10]));
+        m.expect(v);
+        assert_eq!(radio.poll_receive().unwrap(), Some(RxEvent::HeaderValid));
+        m.finalise();
+
+        // No pending events
+        m.expect(vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        assert_eq!(radio.poll_receive().unwrap(), None);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_check_receive_errors() {
+        use radio::Receive as _;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let cases = [
+            (Irq::CRC_ERROR | Irq::RX_DONE, Error::InvalidCrc),
+            (Irq::HEADER_ERROR, Error::InvalidHeader),
+            (Irq::RX_TX_TIMEOUT, Error::PreambleTimeout),
+            (Irq::RX_TX_TIMEOUT | Irq::PREAMBLE_DETECTED, Error::Timeout),
+            (Irq::SYNCWORD_ERROR, Error::InvalidSync),
+        ];
+
+        for (irq, err) in cases.iter() {
+            let mut v = vectors::get_irq(&spi, &sdn, &delay, *irq);
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8,
+                &[(irq.bits() >> 8) as u8, irq.bits() as u8]));
+            m.expect(v);
+
+            assert_eq!(radio.check_receive(false), Err(err.clone()));
+            m.finalise();
+        }
+    }
+
+    #[test]
+    fn test_api_receive_timeout_after_preamble() {
+        use radio::Receive as _;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Preamble detected (and cleared) in an earlier poll than the timeout
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x80, 0x00]));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::RX_TX_TIMEOUT));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x40, 0x00]));
+        m.expect(v);
+
+        assert_eq!(radio.check_receive(false), Ok(false));
+        assert_eq!(radio.check_receive(false), Err(Error::Timeout));
+        m.finalise();
+
+        // Likewise when polling for events
+        radio.preamble_detected = false;
+
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::PREAMBLE_DETECTED);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x80, 0x00]));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::RX_TX_TIMEOUT));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0xC0, 0x00]));
+        m.expect(v);
+
+        assert_eq!(radio.poll_receive(), Ok(Some(RxEvent::PreambleDetected)));
+        assert_eq!(radio.poll_receive(), Ok(Some(RxEvent::Timeout)));
+        m.finalise();
+    }
+
+    #[test]
+    #[cfg(feature = "framing")]
+    fn test_framing() {
+        use crate::framing::*;
+
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+
+        let payload = [0x01, 0x02, 0x03, 0x04];
+        let mut framer = Framer::new();
+
+        let framed = framer.frame(&payload).unwrap();
+        assert_eq!(framed.len(), payload.len() + FRAME_OVERHEAD);
+        assert_eq!(framed[0], payload.len() as u8);
+
+        // Round trip, including with trailing padding
+        let mut buff = [0u8; 16];
+        buff[..framed.len()].copy_from_slice(framed);
+        assert_eq!(deframe(&buff[..framed.len()]), Ok(&payload[..]));
+        assert_eq!(deframe(&buff), Ok(&payload[..]));
+
+        // Corrupted payload or CRC
+        let n = payload.len() + FRAME_OVERHEAD;
+        for i in 1..n {
+            let mut c = buff;
+            c[i] ^= 0x10;
+            assert_eq!(deframe(&c[..n]), Err(ValidationError::InvalidCrc));
+        }
+
+        // Truncated packets and corrupted lengths
+        assert_eq!(deframe(&buff[..n - 1]), Err(ValidationError::InvalidLength));
+        assert_eq!(deframe(&[]), Err(ValidationError::InvalidLength));
+        let mut c = buff;
+        c[0] = 0xF0;
+        assert_eq!(deframe(&c), Err(ValidationError::InvalidLength));
+
+        // Oversized payloads
+        assert_eq!(framer.frame(&[0u8; FRAME_PAYLOAD_MAX + 1]), Err(ValidationError::InvalidLength));
+        assert!(framer.frame(&[0u8; FRAME_PAYLOAD_MAX]).is_ok());
+    }
+
+    #[test]
+    fn test_api_buffer_base() {
+        use radio::Transmit as _;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.config = Config::gfsk();
+        radio.packet_type = PacketType::Gfsk;
+
+        assert_eq!(radio.buffer_base(), (0, 0));
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[128, 0]));
+        radio.set_buffer_base(128, 0).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.buffer_base(), (128, 0));
+
+        let c = match &radio.config.modem {
+            Modem::Gfsk(c) => c.clone(),
+            _ => unreachable!(),
+        };
+        let data = [0x11, 0x22, 0x33, 0x44];
+        let irqs = Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT;
+
+        // Explicit base addresses are preserved and TX data written at the TX base
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8,
+            data.len() as u8, c.crc_mode as u8, c.whitening as u8,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[128, 0]));
+        v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 128, &data));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+            (irqs.bits() >> 8) as u8, irqs.bits() as u8, (irqs.bits() >> 8) as u8, irqs.bits() as u8, 0, 0, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetTx as u8, &[0, 0, 0]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        m.expect(v);
+
+        radio.start_transmit(&data).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_regulator_mode() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Explicit modes are written as-is
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRegulatorMode as u8, &[RegulatorMode::Dcdc as u8]));
+        radio.set_regulator_mode(RegulatorMode::Dcdc).unwrap();
+        m.finalise();
+
+        // Auto selects DC/DC for high power configurations
+        radio.config.pa_config.power = 13;
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRegulatorMode as u8, &[RegulatorMode::Dcdc as u8]));
+        radio.set_regulator_mode(RegulatorMode::Auto).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config.regulator_mode, RegulatorMode::Auto);
+
+        // And LDO for low power configurations
+        radio.config.pa_config.power = 0;
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRegulatorMode as u8, &[RegulatorMode::Ldo as u8]));
+        radio.set_regulator_mode(RegulatorMode::Auto).unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_fragment_round_trip() {
+        use crate::fragment::*;
+
+        let mut data = [0u8; 600];
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = i as u8;
+        }
+
+        // Maximum length fragments, and variable length FLRC packets
+        for (frag_len, expected) in [(FRAGMENT_MAX, 3), (crate::device::flrc::FLRC_PAYLOAD_MAX as usize, 5)].iter() {
+            let count = fragment_count(data.len(), *frag_len);
+            assert_eq!(count, *expected);
+
+            let mut out = [0u8; 600];
+            let mut r = Reassembler::new(&mut out);
+            let mut buff = [0u8; FRAGMENT_MAX];
+
+            for i in 0..count {
+                let n = fragment(&data, i, *frag_len, &mut buff);
+                assert!(n <= *frag_len);
+
+                let res = r.push(&buff[..n]).unwrap();
+                match i == count - 1 {
+                    true => assert_eq!(res, Some(data.len())),
+                    false => assert_eq!(res, None),
+                }
+            }
+
+            assert_eq!(&out[..], &data[..]);
+        }
+
+        let mut buff = [0u8; FRAGMENT_MAX];
+
+        // Fixed length padding is discarded
+        let mut out = [0u8; 600];
+        let mut r = Reassembler::new(&mut out);
+
+        let n = fragment(&data[..10], 0, 32, &mut buff);
+        assert_eq!(n, FRAGMENT_HEADER_LEN + 10);
+        assert_eq!(r.push(&buff[..32]), Ok(Some(10)));
+        assert_eq!(&out[..10], &data[..10]);
+
+        // Missed fragments are rejected
+        let mut out = [0u8; 600];
+        let mut r = Reassembler::new(&mut out);
+
+        let n = fragment(&data, 0, FRAGMENT_MAX, &mut buff);
+        r.push(&buff[..n]).unwrap();
+        let n = fragment(&data, 2, FRAGMENT_MAX, &mut buff);
+        assert_eq!(r.push(&buff[..n]), Err(ValidationError::InvalidSequence));
+
+        // As are messages exceeding the output buffer
+        let mut out = [0u8; 300];
+        let mut r = Reassembler::new(&mut out);
+
+        let n = fragment(&data, 0, FRAGMENT_MAX, &mut buff);
+        r.push(&buff[..n]).unwrap();
+        let n = fragment(&data, 1, FRAGMENT_MAX, &mut buff);
+        assert_eq!(r.push(&buff[..n]), Err(ValidationError::InvalidLength));
+
+        // And truncated fragments
+        let n = fragment(&data, 0, FRAGMENT_MAX, &mut buff);
+        assert_eq!(r.push(&buff[..n - 1]), Err(ValidationError::InvalidLength));
+    }
+
+    #[test]
+    fn test_api_transmit_long_fragment_size() {
+        use crate::device::common::GfskFlrcPacketLength;
+
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Fixed length packets too short for a fragment header are rejected prior to any SPI traffic
+        let mut config = Config::flrc();
+        if let Modem::Flrc(c) = &mut config.modem {
+            c.header_type = GfskFlrcPacketLength::Fixed;
+            c.payload_length = crate::fragment::FRAGMENT_HEADER_LEN as u8;
+        }
+        radio.config = config;
+
+        m.expect(vectors::none());
+        assert_eq!(radio.transmit_long(&[0u8; 16]), Err(Error::InvalidConfiguration));
+        m.finalise();
+
+        // Messages requiring more than 255 fragments are rejected
+        radio.config = Config::flrc();
+        let data = [0u8; 255 * (crate::device::flrc::FLRC_PAYLOAD_MAX as usize - crate::fragment::FRAGMENT_HEADER_LEN) + 1];
+
+        m.expect(vectors::none());
+        assert_eq!(radio.transmit_long(&data), Err(Error::InvalidLength));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_pingpong() {
+        use core::cell::Cell;
+        use core::time::Duration;
+        use embedded_hal::blocking::delay::DelayUs;
+        use crate::pingpong::pingpong;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let config = Config::default();
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        // Poll delays advance the clock used for round-trip times
+        struct Clock<'a>(&'a Cell<u64>);
+
+        impl<'a> DelayUs<u32> for Clock<'a> {
+            type Error = ();
+
+            fn try_delay_us(&mut self, us: u32) -> Result<(), ()> {
+                self.0.set(self.0.get() + us as u64);
+                Ok(())
+            }
+        }
+
+        let now = Cell::new(0u64);
+
+        let irq = |irq: Irq| {
+            let b = irq.bits();
+            let mut v = vectors::get_irq(&spi, &sdn, &delay, irq);
+            if !irq.is_empty() {
+                v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[(b >> 8) as u8, b as u8]));
+            }
+            v
+        };
+        let ping = |seq: u32| {
+            let mut v = vectors::start_transmit(&spi, &sdn, &delay, &seq.to_le_bytes());
+            v.append(&mut irq(Irq::TX_DONE));
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+            v.append(&mut vectors::start_receive(&spi, &sdn, &delay));
+            v
+        };
+        let echo = |seq: u32| {
+            let mut v = irq(Irq::RX_DONE);
+            v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[4, 0]));
+            v.append(&mut vectors::read_buff(&spi, &sdn, &delay, 0, &seq.to_le_bytes()));
+            v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[120, 0x10, 0, 0, 0]));
+            v
+        };
+
+        // First ping echoed after a single empty poll
+        let mut v = ping(0);
+        v.append(&mut irq(Irq::empty()));
+        v.append(&mut echo(0));
+
+        // Second ping lost, timing out after three poll intervals
+        v.append(&mut ping(1));
+        for _ in 0..4 {
+            v.append(&mut irq(Irq::empty()));
+        }
+
+        // Third ping echoed after two empty polls
+        v.append(&mut ping(2));
+        v.append(&mut irq(Irq::empty()));
+        v.append(&mut irq(Irq::empty()));
+        v.append(&mut echo(2));
+        m.expect(v);
+
+        let stats = pingpong(&mut radio, &mut Clock(&now), || now.get(), 3, Duration::from_millis(3), Duration::from_millis(1)).unwrap();
+        m.finalise();
+
+        assert_eq!((stats.sent, stats.received, stats.lost()), (3, 2, 1));
+        assert_eq!((stats.min_us, stats.avg_us, stats.max_us), (1000, 1500, 2000));
+        assert_eq!(now.get(), 6000);
+    }
+
+    #[test]
+    fn test_api_get_syncword() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // GFSK sync words are 5 bytes from the base address
+        radio.packet_type = PacketType::Gfsk;
+        let word = [0x11, 0x22, 0x33, 0x44, 0x55];
+        let reg = Registers::LrSyncWordBaseAddress2 as u16;
+
+        let mut v = vectors::write_regs(&spi, &sdn, &delay, reg, &word);
+        v.append(&mut vectors::read_regs(&spi, &sdn, &delay, reg, &word));
+        m.expect(v);
+
+        radio.set_syncword(2, &word).unwrap();
+        let mut buff = [0u8; 8];
+        assert_eq!(radio.get_syncword(2, &mut buff), Ok(5));
+        assert_eq!(&buff[..5], &word);
+        m.finalise();
+
+        // FLRC sync words are 4 bytes, offset by one
+        radio.packet_type = PacketType::Flrc;
+        let word = [0x12, 0x34, 0x56, 0x78];
+        let reg = Registers::LrSyncWordBaseAddress1 as u16 + 1;
+
+        let mut v = vectors::write_regs(&spi, &sdn, &delay, reg, &word);
+        v.append(&mut vectors::read_regs(&spi, &sdn, &delay, reg, &word));
+        m.expect(v);
+
+        radio.set_syncword(1, &word).unwrap();
+        let mut buff = [0u8; 4];
+        assert_eq!(radio.get_syncword(1, &mut buff), Ok(4));
+        assert_eq!(buff, word);
+        m.finalise();
+
+        // Short buffers are rejected
+        m.expect(vectors::none());
+        assert_eq!(radio.get_syncword(1, &mut [0u8; 3]), Err(Error::InvalidLength));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_set_whitening() {
+        use crate::device::gfsk::GfskConfig;
+        use crate::device::common::WhiteningModes;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::Gfsk;
+
+        let c = GfskConfig::default();
+        radio.config.modem = Modem::Gfsk(c.clone());
+
+        // Only the whitening byte changes
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.sync_word_length as u8, c.sync_word_match as u8, c.header_type as u8,
+            c.payload_length, c.crc_mode as u8, WhiteningModes::RADIO_WHITENING_ON as u8,
+        ]));
+        m.expect(v);
+        radio.set_whitening(true).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config.modem, Modem::Gfsk(GfskConfig{ whitening: WhiteningModes::RADIO_WHITENING_ON, ..c }));
+
+        // LoRa does not support whitening
+        radio.config.modem = Modem::LoRa(Default::default());
+        m.expect(vectors::none());
+        assert_eq!(radio.set_whitening(false), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_lora_header_crc() {
+        use crate::device::lora::{LoRaConfig, LoRaHeader};
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        // Header CRC is implied by explicit headers in the header type byte
+        let c = LoRaConfig::default();
+        assert!(c.header_crc);
+
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, LoRaHeader::Explicit as u8, c.payload_length, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        m.expect(v);
+        radio.configure_modem(&Modem::LoRa(c.clone())).unwrap();
+        m.finalise();
+
+        // Disabling the header CRC is rejected as unsupported
+        let mut config = Config::lora();
+        config.modem = Modem::LoRa(LoRaConfig{ header_crc: false, ..c });
+        assert_eq!(config.validate(), Err(ValidationError::InvalidConfiguration));
+    }
+
+    #[test]
+    fn test_api_lora_preamble_length() {
+        use crate::device::lora::{LoRaConfig, encode_preamble_length, PREAMBLE_LENGTH_MAX};
+
+        // Short preambles fit in the mantissa, longer ones round up to the next exponent step
+        assert_eq!(encode_preamble_length(8), 0x08);
+        assert_eq!(encode_preamble_length(12), 0x0C);
+        assert_eq!(encode_preamble_length(100), 0x3D);
+        assert_eq!(encode_preamble_length(PREAMBLE_LENGTH_MAX), 0xFF);
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let c = LoRaConfig{ preamble_length: 100, ..LoRaConfig::default() };
+
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            0x3D, c.header_type as u8, c.payload_length, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        m.expect(v);
+        radio.configure_modem(&Modem::LoRa(c.clone())).unwrap();
+        m.finalise();
+
+        // Preambles outside of the encodable range are rejected
+        let mut config = Config::lora();
+        config.modem = Modem::LoRa(LoRaConfig{ preamble_length: PREAMBLE_LENGTH_MAX + 1, ..c });
+        assert_eq!(config.validate(), Err(ValidationError::InvalidConfiguration));
+    }
+
+    #[test]
+    fn test_lora_bandwidth_symbol_duration() {
+        use crate::device::lora::{LoRaBandwidth, LoRaSpreadingFactor};
+
+        let bws = [
+            (LoRaBandwidth::Bw200kHz, 203_125),
+            (LoRaBandwidth::Bw400kHz, 406_250),
+            (LoRaBandwidth::Bw800kHz, 812_500),
+            (LoRaBandwidth::Bw1600kHz, 1_625_000),
+        ];
+        for (bw, hz) in bws.iter() {
+            assert_eq!(bw.hz(), *hz);
+        }
+
+        // SF8 at 1600 kHz is 256 chips at 1.625 MHz
+        let c = LoRaChannel{ sf: LoRaSpreadingFactor::Sf8, bw: LoRaBandwidth::Bw1600kHz, ..LoRaChannel::default() };
+        #[cfg(feature = "float")]
+        assert!((c.symbol_duration_us() - 157.538).abs() < 0.01);
+        assert_eq!(c.symbol_time_us(), 157);
+    }
+
+    #[test]
+    fn test_api_self_test() {
+        use driver_pal::PinState;
+
+        let reg = SELF_TEST_REGISTER as u16;
+
+        let expect = |m: &mut Mock, spi: &Spi, readback: u8| {
+            let (sdn, delay) = (m.pin(), m.delay());
+
+            let mut v = vectors::firmware_version(spi, &sdn, &delay, 0xA9B5);
+            v.append(&mut vectors::read_reg(spi, &sdn, &delay, reg, 0x12));
+            v.append(&mut vectors::write_reg(spi, &sdn, &delay, reg, !0x12));
+            v.append(&mut vectors::read_reg(spi, &sdn, &delay, reg, readback));
+            v.append(&mut vectors::write_reg(spi, &sdn, &delay, reg, 0x12));
+            v.append(&mut vectors::write_cmd(spi, &sdn, &delay, Commands::SetStandby as u8, &[0x01]));
+            v.append(&mut vectors::get_status(spi, &sdn, &delay, State::StandbyXosc, CommandStatus::Success));
+            v.append(&mut vectors::write_cmd(spi, &sdn, &delay, Commands::SetStandby as u8, &[0x00]));
+            v.push(vectors::Mt::busy(spi, PinState::Low));
+            m.expect(v);
+        };
+
+        // All checks pass
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        expect(&mut m, &spi, !0x12);
+        let report = radio.self_test().unwrap();
+        assert_eq!(report, SelfTestReport{ firmware_version: 0xA9B5, firmware: true, readback: true, xosc: true, busy: true });
+        assert!(report.passed());
+        m.finalise();
+
+        // Read-back mismatch is reported
+        expect(&mut m, &spi, 0x12);
+        let report = radio.self_test().unwrap();
+        assert!(!report.readback);
+        assert!(!report.passed());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_check_busy_line() {
+        use driver_pal::PinState;
+        use vectors::Mt;
+        use crate::{BUSY_CHECK_SAMPLES, BUSY_CHECK_INTERVAL_US};
+
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // BUSY released after a couple of samples
+        m.expect(vec![
+            Mt::busy(&spi, PinState::High),
+            Mt::delay_us(BUSY_CHECK_INTERVAL_US),
+            Mt::busy(&spi, PinState::High),
+            Mt::delay_us(BUSY_CHECK_INTERVAL_US),
+            Mt::busy(&spi, PinState::Low),
+        ]);
+        assert!(radio.check_busy_line().unwrap());
+        m.finalise();
+
+        // BUSY never released
+        let mut v = vec![];
+        for i in 0..BUSY_CHECK_SAMPLES {
+            v.push(Mt::busy(&spi, PinState::High));
+            if i + 1 < BUSY_CHECK_SAMPLES {
+                v.push(Mt::delay_us(BUSY_CHECK_INTERVAL_US));
+            }
+        }
+        m.expect(v);
+        assert!(!radio.check_busy_line().unwrap());
+        m.finalise();
+    }
+
+    #[test]
+    fn test_gfsk_mod_shaping() {
+        use crate::device::common::ModShaping;
+        use crate::device::gfsk::{GfskChannel, GfskModShaping};
+
+        assert_eq!(GfskChannel::default().ms, GfskModShaping::Off);
+
+        let shaping = [
+            (GfskModShaping::Off, 0x00),
+            (GfskModShaping::Bt1_0, 0x10),
+            (GfskModShaping::Bt0_5, 0x20),
+        ];
+
+        for (ms, raw) in shaping.iter() {
+            let c = GfskChannel{ ms: *ms, ..GfskChannel::default() };
+            assert_eq!(Channel::Gfsk(c.clone()).modulation_params(), [c.br_bw as u8, c.mi as u8, *raw]);
+        }
+
+        // Shared shaping values convert to the same encoding
+        assert_eq!(GfskModShaping::from(ModShaping::Off), GfskModShaping::Off);
+        assert_eq!(GfskModShaping::from(ModShaping::Bt1_0), GfskModShaping::Bt1_0);
+        assert_eq!(GfskModShaping::from(ModShaping::Bt0_5), GfskModShaping::Bt0_5);
+    }
+
+    #[test]
+    fn test_flrc_bitrate_bandwidth() {
+        use crate::device::flrc::{FlrcChannel, FlrcBitrate, FlrcBitrateBandwidth};
+
+        let bitrates = [
+            (FlrcBitrateBandwidth::Br2600Bw2400, FlrcBitrate::BR_2_600_BW_2_4, 0x04),
+            (FlrcBitrateBandwidth::Br2080Bw2400, FlrcBitrate::BR_2_080_BW_2_4, 0x28),
+            (FlrcBitrateBandwidth::Br1300Bw1200, FlrcBitrate::BR_1_300_BW_1_2, 0x45),
+            (FlrcBitrateBandwidth::Br1040Bw1200, FlrcBitrate::BR_1_040_BW_1_2, 0x69),
+            (FlrcBitrateBandwidth::Br650Bw600, FlrcBitrate::BR_0_650_BW_0_6, 0x86),
+            (FlrcBitrateBandwidth::Br520Bw600, FlrcBitrate::BR_0_520_BW_0_6, 0xAA),
+            (FlrcBitrateBandwidth::Br325Bw300, FlrcBitrate::BR_0_325_BW_0_3, 0xC7),
+            (FlrcBitrateBandwidth::Br260Bw300, FlrcBitrate::BR_0_260_BW_0_3, 0xEB),
+        ];
+
+        for (br_bw, legacy, raw) in bitrates.iter() {
+            let c = FlrcChannel{ br_bw: *br_bw, ..FlrcChannel::default() };
+            assert_eq!(Channel::Flrc(c.clone()).modulation_params(), [*raw, c.cr as u8, c.ms as u8]);
+            assert_eq!(FlrcBitrateBandwidth::from(*legacy), *br_bw);
+        }
+    }
+
+    #[test]
+    fn test_api_buffer_layout() {
+        use radio::Transmit;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Overlapping regions are rejected without writing to the device
+        let layout = BufferLayout{ tx_base: 0, tx_len: 200, rx_base: 100, rx_len: 200 };
+        assert_eq!(radio.set_buffer_layout(&layout), Err(Error::InvalidConfiguration));
+
+        // As are regions extending past the end of the buffer
+        let layout = BufferLayout{ tx_base: 0, tx_len: 100, rx_base: 128, rx_len: 255 };
+        assert_eq!(radio.set_buffer_layout(&layout), Err(Error::InvalidConfiguration));
+
+        // Disjoint regions are accepted
+        let layout = BufferLayout{ tx_base: 0, tx_len: 128, rx_base: 128, rx_len: 128 };
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 128]));
+        radio.set_buffer_layout(&layout).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.buffer_base(), (0, 128));
+
+        // TX payloads and staged data are limited to the TX region
+        m.expect(vectors::none());
+        assert_eq!(radio.max_payload_len(), 128);
+        assert_eq!(radio.start_transmit(&[0u8; 200]), Err(Error::InvalidLength));
+        assert_eq!(radio.stage_tx_data(100, &[0u8; 40]), Err(Error::InvalidLength));
+        assert_eq!(radio.transmit_staged(0, 200), Err(Error::InvalidLength));
+
+        // And base addresses are validated against the layout
+        assert_eq!(radio.set_buffer_base(0, 100), Err(Error::InvalidConfiguration));
+        m.finalise();
+
+        assert_eq!(radio.buffer_base(), (0, 128));
+    }
+
+    #[test]
+    fn test_api_scan_channels() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let freqs = [2_440_000_000, 2_450_000_000, 2_460_000_000];
+        let rssi = [180u8, 120, 60];
+        let config = Config::default();
+
+        // Receive mode is entered with the (3 byte) RX timeout
+        radio.set_rx_timeout(Timeout::Configurable{ step: TickSize::TickSize1000us, count: 10 });
+
+        let mut v = vec![];
+        for (f, r) in freqs.iter().zip(rssi.iter()) {
+            let steps = config.freq_to_steps_u64(*f);
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRx as u8, &[0x02, 0x00, 0x0A]));
+            v.push(vectors::Mt::delay_us(100));
+            v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[*r]));
+        }
+
+        // Original frequency is restored on completion
+        let steps = config.freq_to_steps_u64(config.channel.frequency());
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+        m.expect(v);
+
+        let mut results = [(0, 0); 4];
+        let n = radio.scan_channels(&freqs, 100, &mut results).unwrap();
+        m.finalise();
+
+        assert_eq!(n, 3);
+        assert_eq!(&results[..n], &[(2_440_000_000, -90), (2_450_000_000, -60), (2_460_000_000, -30)]);
+    }
+
+    #[test]
+    fn test_api_gfsk_sync_tolerance() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Tolerance is written to the lower nibble, preserving other bits
+        m.expect(vectors::update_reg(&spi, &sdn, &delay, Registers::LrSyncWordTolerance as u16, 0x34, 0x32));
+        radio.set_gfsk_sync_tolerance(2).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.set_gfsk_sync_tolerance(8), Err(Error::InvalidConfiguration));
+    }
+
+    #[test]
+    fn test_api_power_down_on_drop() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        // Disabled by default
+        let radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        m.expect(vectors::none());
+        drop(radio);
+        m.finalise();
+
+        // Sleep issued on drop when enabled
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        let config = Config { power_down_on_drop: true, ..Config::default() };
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0));
+        drop(radio);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_frequency() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let f = 2_450_000_000;
+        let config = Config::default();
+        let steps = config.freq_to_steps_u64(f);
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+        radio.set_frequency(f).unwrap();
+        m.finalise();
+
+        // Cached frequency is within one PLL step of the programmed frequency
+        let step = (config.xtal_freq >> 18) as i64 + 1;
+        let actual = radio.frequency();
+        assert!((f as i64 - actual as i64).abs() <= step);
+
+        // Failed writes leave the cached frequency unchanged
+        m.expect(vectors::busy_timeout(&spi));
+        assert_eq!(radio.set_frequency(2_452_000_000), Err(Error::BusyTimeout));
+        m.finalise();
+        assert_eq!(radio.frequency(), actual);
+
+        // Hops update the cached frequency
+        let table = HopTable::new(&config, &[2_460_000_000]).unwrap();
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, table.get(0).unwrap()));
+        radio.hop_to(&table, 0).unwrap();
+        m.finalise();
+
+        let actual = radio.frequency();
+        assert!((2_460_000_000 - actual as i64).abs() <= step);
+    }
+
+    #[test]
+    fn test_api_rx_settle() {
+        use radio::State as _;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let config = Config { rx_settle_us: 50, ..Config::default() };
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        // Settle delay is inserted on the first RSSI poll after entering RX
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRx as u8, &[0]);
+        v.push(vectors::Mt::delay_us(50));
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[180]));
+        m.expect(v);
+        radio.set_state(State::Rx).unwrap();
+        assert_eq!(radio.poll_rssi_unchecked().unwrap(), -90);
+        m.finalise();
+
+        // But not on subsequent polls
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRssiInst as u8, &[180]));
+        assert_eq!(radio.poll_rssi_unchecked().unwrap(), -90);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_abort() {
+        use radio::State as _;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.rx_queue.pending = true;
+
+        let all = Irq::all().bits();
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[(all >> 8) as u8, all as u8]));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        m.expect(v);
+
+        radio.abort().unwrap();
+        assert!(!radio.rx_queue.pending);
+
+        assert_eq!(radio.get_interrupts(false).unwrap(), Irq::empty());
+        assert_eq!(radio.get_state().unwrap(), State::StandbyRc);
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_abort_from_sleep() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        m.expect(vectors::sleep(&spi, &sdn, &delay, 0x01));
+        radio.sleep(SleepConfig::RETAIN_DATA_RAM).unwrap();
+        m.finalise();
+
+        // Device is woken prior to returning to standby
+        let all = Irq::all().bits();
+
+        let mut v = vectors::wakeup(&spi, &sdn, &delay);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[(all >> 8) as u8, all as u8]));
+        m.expect(v);
+
+        radio.abort().unwrap();
+        m.finalise();
+
+        assert_eq!(radio.sleep_config, None);
+    }
+
+    #[test]
+    fn test_api_soft_reset() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.rx_queue.pending = true;
+
+        let config = radio.config.clone();
+        let all = Irq::all().bits();
+
+        // No NRESET transactions are expected
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[(all >> 8) as u8, all as u8]));
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::ExecutionFailure));
+        v.append(&mut vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        m.expect(v);
+
+        radio.soft_reset().unwrap();
+        m.finalise();
+
+        assert!(!radio.rx_queue.pending);
+        assert_eq!(radio.config, config);
+    }
+
+    #[test]
+    fn test_api_cad_exit_rx() {
+        use crate::device::lora::{LoRaCadSymbols, CadExitMode, CadEvent};
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let cad_irqs = Irq::CAD_DONE | Irq::CAD_ACTIVITY_DETECTED;
+        let rx_irqs = Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT | Irq::SYNCWORD_VALID
+            | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED;
+        let mask = |irqs: Irq| {
+            let b = irqs.bits();
+            [(b >> 8) as u8, b as u8, (b >> 8) as u8, b as u8, 0, 0, 0, 0]
+        };
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetCadParams as u8, &[LoRaCadSymbols::Cad04Symbols as u8]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &mask(cad_irqs)));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetCad as u8, &[]));
+        m.expect(v);
+        radio.start_cad(LoRaCadSymbols::Cad04Symbols, CadExitMode::Rx).unwrap();
+        m.finalise();
+
+        // In progress
+        m.expect(vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        assert_eq!(radio.poll_cad().unwrap(), None);
+        m.finalise();
+
+        // Activity detected switches straight to RX with the primed timeout
+        let t = &radio.config.rf_timeout;
+        let timeout = [t.step() as u8, (t.count() >> 8) as u8, t.count() as u8];
+
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, cad_irqs);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x30, 0x00]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &mask(rx_irqs)));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRx as u8, &timeout));
+        m.expect(v);
+        assert_eq!(radio.poll_cad().unwrap(), Some(CadEvent::Receiving));
+        m.finalise();
+
+        // Without RX exit activity is only reported
+        let mut v = vectors::get_irq(&spi, &sdn, &delay, cad_irqs);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x30, 0x00]));
+        m.expect(v);
+        assert_eq!(radio.poll_cad().unwrap(), Some(CadEvent::Detected));
+        m.finalise();
+    }
+
+    #[test]
+    #[cfg(feature = "float")]
+    fn test_api_lora_sensitivity() {
+        use crate::device::lora::{LoRaBandwidth, LoRaSpreadingFactor};
+
+        let mut m = Mock::new();
+        let spi = m.spi();
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Datasheet reference sensitivities
+        let refs = [
+            (LoRaSpreadingFactor::Sf12, LoRaBandwidth::Bw200kHz, -130.0),
+            (LoRaSpreadingFactor::Sf12, LoRaBandwidth::Bw1600kHz, -121.0),
+        ];
+
+        for (sf, bw, dbm) in refs.iter() {
+            radio.config.channel = Channel::LoRa(LoRaChannel{ sf: *sf, bw: *bw, ..LoRaChannel::default() });
+            let s = radio.lora_sensitivity_dbm().unwrap();
+            assert!((s - dbm).abs() < 1.0, "sensitivity {} expected {}", s, dbm);
+        }
+
+        radio.config.channel = Channel::Gfsk(Default::default());
+        assert_eq!(radio.lora_sensitivity_dbm(), Err(Error::InvalidConfiguration));
+    }
+
+    #[test]
+    fn test_api_configure_rollback() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.config.command_retries = 0;
+
+        let previous = radio.config.clone();
+
+        let mut config = Config::gfsk();
+        config.regulator_mode = RegulatorMode::Dcdc;
+        config.command_retries = 0;
+        let channel = match &config.channel { Channel::Gfsk(c) => c.clone(), _ => unreachable!() };
+        let steps = config.freq_to_steps_u64(channel.freq);
+
+        // Third write (channel configuration) reports an execution failure
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRegulatorMode as u8, &[RegulatorMode::Dcdc as u8]));
+        v.push(vectors::Mt::busy(&spi, driver_pal::PinState::Low));
+        v.push(vectors::Mt::spi_write(&spi, &[Commands::SetRfFrequency as u8], &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+        v.push(vectors::Mt::busy(&spi, driver_pal::PinState::Low));
+        v.push(vectors::Mt::spi_write(&spi, &[Commands::SetPacketType as u8], &[PacketType::Gfsk as u8]));
+        v.push(vectors::Mt::busy(&spi, driver_pal::PinState::Low));
+        v.push(vectors::Mt::spi_write(&spi, &[Commands::SetModulationParams as u8], &[channel.br_bw as u8, channel.mi as u8, channel.ms as u8]));
+        v.push(vectors::Mt::busy(&spi, driver_pal::PinState::Low));
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::ExecutionFailure));
+        m.expect(v);
+
+        assert_eq!(radio.configure(&config), Err(Error::InvalidResponse(CommandStatus::ExecutionFailure as u8)));
+        m.finalise();
+
+        // Driver configuration is unchanged
+        assert_eq!(radio.config, previous);
+    }
+
+    #[test]
+    fn test_api_config_accessors() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        assert_eq!(radio.packet_type(), PacketType::None);
+
+        let mut config = Config { timeout_ms: 123, skip_calibration: true, ..Default::default() };
+
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config(), &config);
+        assert_eq!(radio.packet_type(), PacketType::LoRa);
+
+        // Out-of-range power reports the applied (clamped) value
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        config.pa_config.power = 20;
+
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config().pa_config.power, TX_POWER_MAX);
+
+        // Buffer base addresses are written on configuration
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        config.buffer_base = Some((128, 0));
+
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.buffer_base(), (128, 0));
+        assert!(radio.last_freq.is_some());
+
+        // And return to defaults on reset
+        m.expect(vectors::reset(&spi, &sdn, &delay, config.reset_assert_ms, config.reset_settle_ms));
+        radio.reset().unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config().buffer_base, None);
+        assert_eq!(radio.buffer_layout, None);
+        assert_eq!(radio.last_freq, None);
+    }
+
+    #[test]
+    fn test_api_tx_rx_timeouts() {
+        use radio::{Transmit, Receive};
+        use crate::device::lora::LoRaConfig;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let config = Config { rx_timeout: Some(Timeout::Continuous), ..Config::default() };
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        // Shared timeout is used where unset
+        assert_eq!(radio.tx_timeout_data(), Timeout::Single.data());
+        assert_eq!(radio.rx_timeout_data(), Timeout::Continuous.data());
+
+        let tx_timeout = Timeout::Configurable{ step: TickSize::TickSize1000us, count: 0x0102 };
+        radio.set_tx_timeout(tx_timeout.clone());
+
+        let data = [0x11, 0x22, 0x33];
+        let c = LoRaConfig::default();
+        let mask = |irqs: Irq| {
+            let b = irqs.bits();
+            [(b >> 8) as u8, b as u8, (b >> 8) as u8, b as u8, 0, 0, 0, 0]
+        };
+
+        // Transmit uses the TX timeout
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.header_type as u8, data.len() as u8, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+        v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 0, &data));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &mask(Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT)));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetTx as u8, &[TickSize::TickSize1000us as u8, 0x01, 0x02]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        m.expect(v);
+        radio.start_transmit(&data).unwrap();
+        m.finalise();
+
+        // Receive uses the RX timeout
+        let rx_irqs = Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT | Irq::SYNCWORD_VALID
+            | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED;
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.header_type as u8, c.payload_length, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::LnaRegime as u16, 0x00, 0x00));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &mask(rx_irqs)));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRx as u8, &[TickSize::TickSize0015us as u8, 0xFF, 0xFF]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Rx));
+        m.expect(v);
+        radio.start_receive().unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_reconfigure_on_rx() {
+        use radio::Receive;
+        use crate::device::lora::LoRaConfig;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let config = Config { reconfigure_on_rx: false, ..Config::default() };
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        let c = LoRaConfig::default();
+        let rx_irqs = (Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT | Irq::SYNCWORD_VALID
+            | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED).bits();
+
+        let rx = |configure: bool| {
+            let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+            if configure {
+                v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+                v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+                    c.preamble_length as u8, c.header_type as u8, c.payload_length, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+                ]));
+            }
+            v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::LnaRegime as u16, 0x00, 0x00));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+                (rx_irqs >> 8) as u8, rx_irqs as u8, (rx_irqs >> 8) as u8, rx_irqs as u8, 0, 0, 0, 0,
+            ]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRx as u8, &[0, 0, 0]));
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Rx));
+            v
+        };
+
+        // First receive applies configuration
+        m.expect(rx(true));
+        radio.start_receive().unwrap();
+        m.finalise();
+
+        // Second receive skips unchanged configuration
+        m.expect(rx(false));
+        radio.start_receive().unwrap();
+        m.finalise();
+
+        // Buffer (or modem) changes force reconfiguration
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+        radio.set_buff_base_addr(0, 0).unwrap();
+        m.finalise();
+
+        m.expect(rx(true));
+        radio.start_receive().unwrap();
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_ble_length_bounds() {
+        use radio::Receive;
+        use crate::device::ble::{BleConfig, BleConnectionStates, BleCrcFields, BlePacketTypes};
+        use crate::device::common::WhiteningModes;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.config.modem = Modem::Ble(BleConfig{
+            connection_state: BleConnectionStates::BLE_PAYLOAD_LENGTH_MAX_255_BYTES,
+            crc_field: BleCrcFields::BLE_CRC_3B,
+            packet_type: BlePacketTypes::BLE_PRBS_9,
+            whitening: WhiteningModes::RADIO_WHITENING_OFF,
+        });
+
+        let mut info = PacketInfo::default();
+        let mut data = [0u8; 255];
+
+        // Oversized lengths are rejected rather than overflowing
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[0xFE, 0x00]));
+        assert_eq!(radio.get_received(&mut info, &mut data), Err(Error::InvalidLength));
+        m.finalise();
+
+        // As are lengths exceeding the provided buffer
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[0x10, 0x00]));
+        assert_eq!(radio.get_received(&mut info, &mut data[..16]), Err(Error::InvalidLength));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_get_received_short_buffer() {
+        use radio::Receive;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let mut info = PacketInfo::default();
+        let mut data = [0u8; 4];
+
+        // 32-byte packet reported with a 4-byte buffer returns an error without reading the buffer
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[32, 0x00]));
+        assert_eq!(radio.get_received(&mut info, &mut data), Err(Error::InvalidLength));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_receive_into() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let mut buf = [0u8; 16];
+
+        let mut v = vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[data.len() as u8, 0x10]);
+        v.append(&mut vectors::read_buff(&spi, &sdn, &delay, 0x10, &data));
+        v.append(&mut vectors::read_cmd(&spi, &sdn, &delay, Commands::GetPacketStatus as u8, &[120, 0x14, 0, 0, 0x01]));
+        m.expect(v);
+
+        let (n, info) = radio.receive_into(&mut buf).unwrap();
+        m.finalise();
+
+        assert_eq!(&buf[..n], &data);
+        assert_eq!(info.rssi, -60);
+        assert_eq!(info.snr, Some(5));
+        assert_eq!(info.sync_addr_status, 0x01);
+    }
+
+    #[test]
+    fn test_irq_active_names() {
+        use std::vec::Vec;
+
+        let irq = Irq::RX_DONE | Irq::CRC_ERROR;
+        let names: Vec<_> = irq.active_names().collect();
+        assert_eq!(names, vec!["RX_DONE", "CRC_ERROR"]);
+
+        assert_eq!(Irq::empty().active_names().count(), 0);
+        assert_eq!(Irq::all().active_names().count(), 16);
+    }
+
+    #[test]
+    fn test_calibration_params() {
+        assert_eq!(CalibrationParams::all().bits(), 0x3F);
+        assert_eq!(CalibrationParams::none().bits(), 0x00);
+
+        // Selective calibration sets only the chosen bits
+        let c = CalibrationParams::none().with_adc().with_pll();
+        assert_eq!(c.bits(), 0x3C);
+
+        let c = CalibrationParams::none().with_rc13m().with_rc64k();
+        assert_eq!(c, CalibrationParams::RC13MEnable | CalibrationParams::RC64KEnable);
+    }
+
+    #[test]
+    fn test_api_transmit_all() {
+        use crate::device::lora::LoRaConfig;
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+        radio.config.timeout_ms = 2;
+
+        let c = LoRaConfig::default();
+        let tx_irqs = (Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT).bits();
+
+        let tx = |data: &[u8]| {
+            let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+                c.preamble_length as u8, c.header_type as u8, data.len() as u8, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+            ]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+            v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 0, data));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+                (tx_irqs >> 8) as u8, tx_irqs as u8, (tx_irqs >> 8) as u8, tx_irqs as u8, 0, 0, 0, 0,
+            ]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetTx as u8, &[0, 0, 0]));
+            v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+            v
+        };
+        let done = || {
+            let mut v = vectors::get_irq(&spi, &sdn, &delay, Irq::TX_DONE);
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x01]));
+            v
+        };
+
+        let packets: [&[u8]; 3] = [&[0x11], &[0x22, 0x33], &[0x44, 0x55, 0x66]];
+
+        // All packets sent
+        let mut v = vec![];
+        for p in packets.iter() {
+            v.append(&mut tx(p));
+            v.append(&mut done());
+        }
+        m.expect(v);
+        assert_eq!(radio.transmit_all(packets.iter().cloned()), Ok(3));
+        m.finalise();
+
+        // Second packet times out
+        let mut v = tx(packets[0]);
+        v.append(&mut done());
+        v.append(&mut tx(packets[1]));
+        for _ in 0..2 {
+            v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+            v.push(vectors::Mt::delay_ms(1));
+        }
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        m.expect(v);
+        assert_eq!(radio.transmit_all(packets.iter().cloned()), Err((1, Error::Timeout)));
+        m.finalise();
+    }
+
+    #[test]
+    fn test_api_new_initial_standby() {
+        let mut config = Config {
+            skip_calibration: true,
+            initial_standby: State::StandbyXosc,
+            ..Default::default()
+        };
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+
+        // STDBY_XOSC is selected on configuration and on completion
+        let v = vectors::new_sequence(&spi, &sdn, &delay, &config);
+        assert!(v.contains(&vectors::Mt::spi_write(&spi, &[Commands::SetStandby as u8], &[0x01])));
+        m.expect(v);
+
+        let _radio = Sx128x::<Spi, _, _, _>::new(spi.clone(), &config).unwrap();
+        m.finalise();
+
+        // Non-standby states are rejected
+        config.initial_standby = State::Rx;
+        assert_eq!(config.validate(), Err(ValidationError::InvalidConfiguration));
+    }
+
+    #[test]
+    fn test_config_freq_to_steps_u64() {
+        let config = Config::default();
+
+        // 2.5 GHz * 2^18 / 52 MHz = 12603076.92, rounded to the nearest step
+        let f = 2_500_000_000;
+        let exact = config.freq_to_steps_u64(f);
+        assert_eq!(exact, 12_603_077);
+
+        // Float conversion is at best within a step of the integer result
+        #[cfg(feature = "float")]
+        {
+            let float = config.freq_to_steps(f as f32) as u32;
+            assert!((exact as i64 - float as i64).abs() <= 1);
+        }
 
-        let irq = self.get_interrupts(true)?;
-        let state = self.get_state()?;
+        // Conversion back is within a step of the requested frequency
+        let back = config.steps_to_freq(exact);
+        assert!((back as i64 - f as i64).abs() <= (config.xtal_freq >> 18) as i64 + 1);
 
-        trace!("TX poll (irq: {:?}, state: {:?})", irq, state);
+        // Band edges
+        assert_eq!(config.freq_to_steps_u64(2_400_000_000), 12_098_954);
+        assert_eq!(config.steps_to_freq(12_098_954), 2_400_000_030);
 
-        if irq.contains(Irq::TX_DONE) {
-            debug!("TX complete");
-            Ok(true)
-        } else if irq.contains(Irq::RX_TX_TIMEOUT) {
-            debug!("TX timeout");
-            Err(Error::Timeout)
-        } else {
-            Ok(false)
-        }
+        // Alternate reference
+        let config = Config{ xtal_freq: 32_000_000, ..Config::default() };
+        assert_eq!(config.freq_to_steps_u64(2_400_000_000), 19_660_800);
+        assert_eq!(config.steps_to_freq(19_660_800), 2_400_000_000);
     }
-}
 
-/// `radio::Receive` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Receive for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    /// Receive info structure
-    type Info = PacketInfo;
+    #[test]
+    fn test_timeout_from_micros() {
 
-    /// RF Error object
-    type Error = Error<CommsError, PinError, DelayError>;
+        // Short timeouts use the finest step, rounding up
+        assert_eq!(Timeout::from_micros(100), Ok(Timeout::Configurable{ step: TickSize::TickSize0015us, count: 7 }));
 
-    /// Start radio in receive mode
-    fn start_receive(&mut self) -> Result<(), Self::Error> {
-        debug!("RX start");
+        // 1 s still fits the finest step
+        assert_eq!(Timeout::from_micros(1_000_000), Ok(Timeout::Configurable{ step: TickSize::TickSize0015us, count: 64_000 }));
 
-        // Set state to idle before we write configuration
-        self.set_state(State::StandbyRc)?;
+        // Longer timeouts select a coarser step
+        assert_eq!(Timeout::from_micros(10_000_000), Ok(Timeout::Configurable{ step: TickSize::TickSize1000us, count: 10_000 }));
 
-        let s = self.get_state()?;
-        debug!("RX setup state: {:?}", s);
+        // Over-range and zero durations are rejected
+        assert_eq!(Timeout::from_micros(300_000_000), Err(ValidationError::InvalidConfiguration));
+        assert_eq!(Timeout::from_micros(0), Err(ValidationError::InvalidConfiguration));
+    }
 
-        // Reset buffer addr
-        if let Err(e) = self.set_buff_base_addr(0, 0)  {
-            let s = self.get_state();
-            error!("RX error setting buffer base addr (error: {:?}, state: {:?})", e, s);
-            return Err(e);
-        }
-        
-        // Set packet mode
-        // TODO: surely this should not bre required _every_ receive?
-        let modem_config = self.config.modem.clone();
-        
-        if let Err(e) = self.configure_modem(&modem_config) {
-            let s = self.get_state();
-            error!("RX error setting configuration (error: {:?}, state: {:?})", e, s);
-            return Err(e);
+    #[test]
+    fn test_api_xtal_ppb_offset() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        let f = 2_450_000_000;
+        assert_eq!(radio.config.freq_to_steps_u64(f), 12_351_015);
+
+        // A crystal running 20 ppm fast requires 20 ppm fewer steps (~247 at 2.45 GHz)
+        let config = Config { xtal_ppb_offset: 20_000, ..Config::default() };
+        m.expect(vectors::configure_sequence(&spi, &sdn, &delay, &config));
+        radio.configure(&config).unwrap();
+        m.finalise();
+
+        let steps = radio.config.freq_to_steps_u64(f);
+        assert_eq!(steps, 12_350_768);
+        #[cfg(feature = "float")]
+        assert!((radio.config.freq_to_steps(f as f32) as i64 - steps as i64).abs() <= 1);
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8,
+            &[(steps >> 16) as u8, (steps >> 8) as u8, steps as u8]));
+        radio.set_frequency(f).unwrap();
+        m.finalise();
+
+        // Read back is corrected to the actual RF frequency
+        let actual = radio.frequency();
+        assert!((f as i64 - actual as i64).abs() <= 200);
+    }
+
+    #[test]
+    fn test_config_modem_defaults() {
+        let configs = [
+            (Config::lora(), PacketType::LoRa),
+            (Config::flrc(), PacketType::Flrc),
+            (Config::gfsk(), PacketType::Gfsk),
+            (Config::ble(), PacketType::Ble),
+        ];
+
+        for (c, packet_type) in configs.iter() {
+            assert_eq!(c.validate(), Ok(()), "config: {:?}", c);
+            assert_eq!(&c.packet_type, packet_type);
+            assert_eq!(PacketType::from(&c.modem), *packet_type);
+            assert_eq!(c.channel.frequency(), 2_440_000_000);
         }
+    }
 
-        // Configure ranging if used
-        if PacketType::Ranging == self.packet_type {
-            self.hal.write_cmd(Commands::SetRangingRole as u8, &[ RangingRole::Responder as u8 ])?;
+    #[test]
+    fn test_api_get_raw_status() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+
+        // Reserved and invalid bits are returned unmodified
+        for raw in [0x00, 0x43, 0xFF].iter() {
+            m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetStatus as u8, &[*raw]));
+            assert_eq!(radio.get_raw_status().unwrap(), *raw);
+            m.finalise();
         }
+    }
 
-        // Setup timout
-        let config = [
-            self.config.rf_timeout.step() as u8,
-            (( self.config.rf_timeout.count() >> 8 ) & 0x00FF ) as u8,
-            (self.config.rf_timeout.count() & 0x00FF ) as u8,
-        ];
-        
-        // Enable IRQs
-        let irqs = Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT
-        | Irq::SYNCWORD_VALID | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID
-        | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED;
+    #[test]
+    fn test_api_state_guard() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
 
-        self.set_irq_dio_mask(
-            irqs, irqs, DioMask::empty(), DioMask::empty()
-        )?;
+        let channel = LoRaChannel::default();
+        let steps = radio.config.freq_to_steps_u64(channel.freq);
+        let modulation = [channel.sf as u8, channel.bw as u8, channel.cr as u8];
 
-        // Enter transmit mode
-        self.hal.write_cmd(Commands::SetRx as u8, &config)?;
+        // Channel and modem changes are refused while transmitting
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        assert_eq!(radio.set_channel(&Channel::LoRa(channel.clone())), Err(Error::InvalidState(State::StandbyRc, State::Tx)));
+        m.finalise();
 
-        let state = self.get_state()?;
+        let modem = radio.config.modem.clone();
+        m.expect(vectors::get_state(&spi, &sdn, &delay, State::Rx));
+        assert_eq!(radio.configure_modem(&modem), Err(Error::InvalidState(State::StandbyRc, State::Rx)));
+        m.finalise();
 
-        debug!("RX started (state: {:?})", state);
+        // FS mode is permitted
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::Fs);
+        v.append(&mut vectors::set_channel(&spi, &sdn, &delay, steps, Some(PacketType::LoRa as u8), &modulation));
+        m.expect(v);
+        radio.set_channel(&Channel::LoRa(channel.clone())).unwrap();
+        m.finalise();
 
-        Ok(())
+        // Checks are skipped entirely when bypassed
+        radio.config.unsafe_state_transitions = true;
+        m.expect(vectors::set_channel(&spi, &sdn, &delay, steps, None, &modulation));
+        radio.set_channel(&Channel::LoRa(channel)).unwrap();
+        m.finalise();
     }
 
-    /// Check for a received packet
-    fn check_receive(&mut self, restart: bool) -> Result<bool, Self::Error> {
+    #[test]
+    fn test_api_activity_hook() {
+        use std::cell::RefCell;
+        use std::vec::Vec;
+        use radio::Transmit;
 
-        // Poll on DIO and short-circuit if not asserted
-        #[cfg(feature = "poll_irq")]
-        if self.hal.get_dio()? == PinState::Low {
-            return Ok(false)
+        std::thread_local! {
+            static ACTIVITY: RefCell<Vec<Activity>> = const { RefCell::new(Vec::new()) };
         }
 
-        let irq = self.get_interrupts(true)?;
-        let mut res = Ok(false);
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+        radio.set_activity_hook(|a| ACTIVITY.with(|v| v.borrow_mut().push(a)));
 
-        trace!("RX poll (irq: {:?})", irq);
-       
-        // Process flags
-        if irq.contains(Irq::CRC_ERROR) {
-            debug!("RX CRC error");
-            res = Err(Error::InvalidCrc);
-        } else if irq.contains(Irq::RX_TX_TIMEOUT) {
-            debug!("RX timeout");
-            res = Err(Error::Timeout);
-        } else if irq.contains(Irq::SYNCWORD_ERROR) {
-            debug!("Invalid syncword");
-            res = Err(Error::InvalidSync);
-        } else if irq.contains(Irq::RX_DONE) {
-            debug!("RX complete");
-            res = Ok(true);
-        }
+        let data = [0x11, 0x22, 0x33];
 
-        // Auto-restart on failure if enabled
-        match (restart, res) {
-            (true, Err(_)) => {
-                debug!("RX restarting");
-                self.start_receive()?;
-                Ok(false)
-            },
-            (_, r) => r
+        let mut v = vectors::start_transmit(&spi, &sdn, &delay, &data);
+
+        // Transmit still in progress
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::empty()));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+
+        // Transmit complete
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::TX_DONE));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x01]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        m.expect(v);
+
+        radio.start_transmit(&data).unwrap();
+        assert!(!radio.check_transmit().unwrap());
+        assert!(radio.check_transmit().unwrap());
+        m.finalise();
+
+        ACTIVITY.with(|v| assert_eq!(*v.borrow(), vec![Activity::TxStart, Activity::TxDone]));
+
+        // Blocking transmits complete via wait_irq
+        ACTIVITY.with(|v| v.borrow_mut().clear());
+
+        let mut v = vectors::start_transmit(&spi, &sdn, &delay, &data);
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::TX_DONE));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x01]));
+        m.expect(v);
+
+        assert_eq!(radio.transmit_all([&data[..]].iter().cloned()), Ok(1));
+        m.finalise();
+
+        ACTIVITY.with(|v| assert_eq!(*v.borrow(), vec![Activity::TxStart, Activity::TxDone]));
+    }
+
+    #[test]
+    fn test_api_state_change_hook() {
+        use std::cell::RefCell;
+        use std::vec::Vec;
+        use radio::Transmit;
+
+        std::thread_local! {
+            static STATES: RefCell<Vec<(State, State)>> = const { RefCell::new(Vec::new()) };
         }
+
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+        radio.set_state_change_hook(|from, to| STATES.with(|v| v.borrow_mut().push((from, to))));
+
+        let data = [0x11, 0x22, 0x33];
+
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[1]);
+
+        // Transmit cycle
+        v.append(&mut vectors::start_transmit(&spi, &sdn, &delay, &data));
+        v.append(&mut vectors::get_irq(&spi, &sdn, &delay, Irq::TX_DONE));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[0x00, 0x01]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+
+        // Return to standby, repeated requests are not reported
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+        m.expect(v);
+
+        radio.set_state(State::StandbyXosc).unwrap();
+        radio.start_transmit(&data).unwrap();
+        assert!(radio.check_transmit().unwrap());
+        radio.set_state(State::StandbyRc).unwrap();
+        radio.set_state(State::StandbyRc).unwrap();
+        m.finalise();
+
+        STATES.with(|v| assert_eq!(*v.borrow(), vec![
+            (State::StandbyRc, State::StandbyXosc),
+            (State::StandbyXosc, State::StandbyRc),
+            (State::StandbyRc, State::Tx),
+            (State::Tx, State::StandbyRc),
+        ]));
+
+        // Reset returns to standby from any state
+        STATES.with(|v| v.borrow_mut().clear());
+
+        let mut v = vectors::sleep(&spi, &sdn, &delay, 0x00);
+        v.append(&mut vectors::reset(&spi, &sdn, &delay, radio.config.reset_assert_ms, radio.config.reset_settle_ms));
+        m.expect(v);
+
+        radio.sleep(SleepConfig::empty()).unwrap();
+        radio.reset().unwrap();
+        m.finalise();
+
+        assert_eq!(radio.sleep_config, None);
+        assert_eq!(radio.packet_type(), PacketType::None);
+        STATES.with(|v| assert_eq!(*v.borrow(), vec![
+            (State::StandbyRc, State::Sleep),
+            (State::Sleep, State::StandbyRc),
+        ]));
     }
 
-    /// Fetch a received packet
-    fn get_received<'a>(&mut self, info: &mut Self::Info, data: &'a mut [u8]) -> Result<usize, Self::Error> {
-        // Fetch RX buffer information
-        let (ptr, len) = self.get_rx_buffer_status()?;
+    #[test]
+    fn test_api_max_payload_len() {
+        use radio::Transmit;
+        use crate::device::gfsk::GfskConfig;
+        use crate::device::common::GfskFlrcPacketLength;
 
-        debug!("RX get received, ptr: {} len: {}", ptr, len);
+        let mut m = Mock::new();
+        let (spi, _sdn, _busy, _delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
 
-        if data.len() < len as usize {
-            return Err(Error::InvalidLength);
-        }
+        // LoRa explicit header packets may use the full 255 bytes
+        assert_eq!(radio.max_payload_len(), 255);
 
-        // TODO: check error packet status byte to ensure CRC is valid
-        // as this may not result in a CRC error IRQ.
-        // See chip errata for further details
+        // Fixed length GFSK packets are limited to the configured length
+        radio.config.modem = Modem::Gfsk(GfskConfig{ header_type: GfskFlrcPacketLength::Fixed, payload_length: 16, ..Default::default() });
+        assert_eq!(radio.max_payload_len(), 16);
 
-        // Read from the buffer at the provided pointer
-        self.hal.read_buff(ptr, &mut data[..len as usize])?;
+        // Over-length payloads are rejected rather than truncated
+        m.expect(vectors::none());
+        assert_eq!(radio.start_transmit(&[0xAA; 17]), Err(Error::InvalidLength));
+        m.finalise();
 
-        // Fetch related information
-        self.get_packet_info(info)?;
+        radio.config.modem = Modem::LoRa(Default::default());
+        m.expect(vectors::none());
+        assert_eq!(radio.start_transmit(&[0xAA; 256]), Err(Error::InvalidLength));
+        m.finalise();
+        // Including staged packets
+        radio.config.modem = Modem::Flrc(Default::default());
+        m.expect(vectors::none());
+        assert_eq!(radio.transmit_staged(0, 200), Err(Error::InvalidLength));
+        m.finalise();
+    }
 
-        trace!("RX data: {:?} info: {:?}", &data[..len as usize], info);
+    #[test]
+    fn test_api_transmit_staged() {
+        use crate::device::lora::LoRaConfig;
 
-        // Return read length
-        Ok(len as usize)
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let c = LoRaConfig::default();
+        let tx_irqs = (Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT).bits();
+
+        // Stage data once
+        m.expect(vectors::write_buff(&spi, &sdn, &delay, 0x80, &[0x11, 0x22, 0x33]));
+        radio.stage_tx_data(0x80, &[0x11, 0x22, 0x33]).unwrap();
+        m.finalise();
+
+        // Transmit issues SetTx from the staged offset without a buffer write
+        let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.header_type as u8, 3, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0x80, 0]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+            (tx_irqs >> 8) as u8, tx_irqs as u8, (tx_irqs >> 8) as u8, tx_irqs as u8, 0, 0, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetTx as u8, &[0, 0, 0]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        m.expect(v);
+        radio.transmit_staged(0x80, 3).unwrap();
+        m.finalise();
+
+        // Data exceeding the buffer is rejected
+        m.expect(vectors::none());
+        assert_eq!(radio.stage_tx_data(0xFE, &[0x11, 0x22, 0x33]), Err(Error::InvalidLength));
+        assert_eq!(radio.transmit_staged(0x80, 0x81), Err(Error::InvalidLength));
+        m.finalise();
     }
 
-}
+    #[test]
+    fn test_api_transmit_csma() {
+        use crate::device::lora::{LoRaConfig, LoRaCadSymbols};
 
-/// `radio::Rssi` implementation for the SX128x
-impl<Hal, CommsError, PinError, DelayError> radio::Rssi for Sx128x<Hal, CommsError, PinError, DelayError>
-where
-    Hal: base::Hal<CommsError, PinError, DelayError>,
-    CommsError: Debug + Sync + Send + 'static,
-    PinError: Debug + Sync + Send + 'static,
-    DelayError: Debug + Sync + Send + 'static,
-{
-    type Error = Error<CommsError, PinError, DelayError>;
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let cad_irqs = Irq::CAD_DONE | Irq::CAD_ACTIVITY_DETECTED;
+        let cad = |irq: Irq| {
+            let mut v = vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]);
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetCadParams as u8, &[LoRaCadSymbols::Cad04Symbols as u8]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+                (cad_irqs.bits() >> 8) as u8, cad_irqs.bits() as u8, (cad_irqs.bits() >> 8) as u8, cad_irqs.bits() as u8, 0, 0, 0, 0,
+            ]));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetCad as u8, &[]));
+            v.append(&mut vectors::get_irq(&spi, &sdn, &delay, irq));
+            v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::ClearIrqStatus as u8, &[(irq.bits() >> 8) as u8, irq.bits() as u8]));
+            v
+        };
 
-    /// Poll for the current channel RSSI
-    /// This should only be called when in receive mode
-    fn poll_rssi(&mut self) -> Result<i16, Error<CommsError, PinError, DelayError>> {
-        let mut raw = [0u8; 1];
-        self.hal.read_cmd(Commands::GetRssiInst as u8, &mut raw)?;
-        Ok(-(raw[0] as i16) / 2)
+        // SF8 at 203.125 kHz, backing off 4 symbols
+        let symbol_us = LoRaChannel::default().symbol_time_us();
+        assert_eq!(symbol_us, 1260);
+
+        let data = [0x11, 0x22, 0x33];
+        let c = LoRaConfig::default();
+        let tx_irqs = Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT;
+
+        // Busy twice, then clear
+        let mut v = cad(cad_irqs);
+        v.push(vectors::Mt::delay_us(4 * symbol_us));
+        v.append(&mut cad(cad_irqs));
+        v.push(vectors::Mt::delay_us(4 * symbol_us));
+        v.append(&mut cad(Irq::CAD_DONE));
+
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetStandby as u8, &[0]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::StandbyRc));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            c.preamble_length as u8, c.header_type as u8, data.len() as u8, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+        v.append(&mut vectors::write_buff(&spi, &sdn, &delay, 0, &data));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetDioIrqParams as u8, &[
+            (tx_irqs.bits() >> 8) as u8, tx_irqs.bits() as u8, (tx_irqs.bits() >> 8) as u8, tx_irqs.bits() as u8, 0, 0, 0, 0,
+        ]));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetTx as u8, &[0, 0, 0]));
+        v.append(&mut vectors::get_state(&spi, &sdn, &delay, State::Tx));
+        m.expect(v);
+
+        assert!(radio.transmit_csma(&data, 3, || 3).unwrap());
+        m.finalise();
+
+        // Giving up once backoffs are exhausted
+        let mut v = cad(cad_irqs);
+        v.push(vectors::Mt::delay_us(4 * symbol_us));
+        v.append(&mut cad(cad_irqs));
+        m.expect(v);
+
+        assert!(!radio.transmit_csma(&data, 1, || 3).unwrap());
+        m.finalise();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{Sx128x};
-    use crate::base::Hal;
-    use crate::device::RampTime;
+    #[test]
+    fn test_api_set_pa_config() {
+        let mut m = Mock::new();
+        let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
 
-    extern crate embedded_spi;
-    use self::driver_pal::mock::{Mock, Spi};
+        // +13 dBm is written as the maximum power register value, with no further PA writes
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x1F, RampTime::Ramp10Us as u8));
+        radio.set_pa_config(13, RampTime::Ramp10Us).unwrap();
+        m.finalise();
 
-    use radio::{State as _};
+        assert_eq!(radio.config.pa_config, PaConfig{ power: 13, ramp_time: RampTime::Ramp10Us });
 
-    pub mod vectors;
+        // Out of range powers are clamped
+        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x00, RampTime::Ramp02Us as u8));
+        radio.set_pa_config(-30, RampTime::Ramp02Us).unwrap();
+        m.finalise();
+
+        assert_eq!(radio.config.pa_config.power, TX_POWER_MIN);
+
+        // Or rejected in strict mode
+        radio.config.strict_power = true;
+        m.expect(vectors::none());
+        assert_eq!(radio.set_pa_config(14, RampTime::Ramp02Us), Err(Error::InvalidConfiguration));
+        m.finalise();
+    }
 
     #[test]
-    fn test_api_reset() {
+    fn test_packet_status_decode() {
+        use std::string::ToString;
+
+        // Packet received with a CRC error
+        let s = PacketStatus::from_bits_truncate(0x12);
+        assert!(s.crc_error() && s.packet_received());
+        assert!(!s.sync_error() && !s.length_error() && !s.abort_error());
+        assert_eq!(s.to_string(), "crc_error|packet_received");
+
+        // Sync and length errors
+        let s = PacketStatus::from_bits_truncate(0x60);
+        assert!(s.sync_error() && s.length_error());
+        assert!(!s.crc_error() && !s.packet_received());
+
+        assert_eq!(PacketStatus::empty().to_string(), "none");
+
+        let s = TxRxStatus::from_bits_truncate(0x01);
+        assert!(s.is_pkt_sent() && !s.rx_no_ack());
+        assert_eq!(s.to_string(), "pkt_sent");
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn test_api_trace() {
+        use std::vec::Vec;
+        use std::rc::Rc;
+        use core::cell::RefCell;
+        use crate::base::{TracedHal, SpiDirection};
+
         let mut m = Mock::new();
         let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
-        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
 
-        m.expect(vectors::reset(&spi, &sdn, &delay));
-        radio.hal.reset().unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let e = events.clone();
+        let hal = TracedHal::new(spi.clone(), move |t: &crate::base::SpiTraceEvent| {
+            e.borrow_mut().push((t.opcode, t.address, t.direction, t.data.to_vec()))
+        });
+
+        let mut radio = Sx128x::<_, _, _, _>::build(hal);
+
+        let f = 2_440_000_000;
+        let steps = radio.config.freq_to_steps_u64(f);
+        let data = [(steps >> 16) as u8, (steps >> 8) as u8, steps as u8];
+
+        m.expect(vectors::write_cmd(&spi, &sdn, &delay, Commands::SetRfFrequency as u8, &data));
+        radio.set_frequency(f).unwrap();
+        m.finalise();
+
+        // Reads are traced on completion
+        m.expect(vectors::read_reg(&spi, &sdn, &delay, Registers::LrFirmwareVersionMsb as u16, 0xA9));
+        radio.hal.read_reg(Registers::LrFirmwareVersionMsb as u16).unwrap();
         m.finalise();
+
+        assert_eq!(&*events.borrow(), &[
+            (Commands::SetRfFrequency as u8, None, SpiDirection::Write, data.to_vec()),
+            (Commands::ReadRegister as u8, Some(Registers::LrFirmwareVersionMsb as u16), SpiDirection::Read, [0xA9].to_vec()),
+        ]);
     }
 
     #[test]
-    fn test_api_status() {
+    fn test_api_rx_buffer_status_fixed() {
+        use crate::device::common::GfskFlrcPacketLength;
+
         let mut m = Mock::new();
         let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
-        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.config = Config::gfsk();
 
-        m.expect(vectors::status(&spi, &sdn, &delay));
-        radio.get_state().unwrap();
+        // Variable length packets report the received length
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[12, 4]));
+        assert_eq!(radio.rx_buffer_status().unwrap(), (4, 12));
+        m.finalise();
+
+        // Fixed length packets report the configured length
+        if let Modem::Gfsk(c) = &mut radio.config.modem {
+            c.header_type = GfskFlrcPacketLength::Fixed;
+            c.payload_length = 32;
+        }
+        radio.config.validate().unwrap();
+
+        m.expect(vectors::read_cmd(&spi, &sdn, &delay, Commands::GetRxBufferStatus as u8, &[0, 4]));
+        assert_eq!(radio.rx_buffer_status().unwrap(), (4, 32));
         m.finalise();
+
+        // Zero length fixed packets are rejected
+        if let Modem::Gfsk(c) = &mut radio.config.modem {
+            c.payload_length = 0;
+        }
+        assert_eq!(radio.config.validate(), Err(ValidationError::InvalidConfiguration));
     }
 
     #[test]
-    fn test_api_firmware_version() {
+    fn test_api_set_modem() {
+        use crate::device::flrc::{FlrcChannel, FlrcConfig};
+        use driver_pal::PinState;
+
         let mut m = Mock::new();
         let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
-        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
+        radio.packet_type = PacketType::LoRa;
+
+        let freq = radio.config.channel.frequency();
+        let channel = FlrcChannel{ freq: 2_480_000_000, ..Default::default() };
+        let modem = FlrcConfig::default();
+        let modulation = Channel::Flrc(channel.clone()).modulation_params();
+
+        // Only packet type, modulation and packet parameters are written (no frequency or PA writes)
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vec![
+            vectors::Mt::busy(&spi, PinState::Low),
+            vectors::Mt::spi_write(&spi, &[Commands::SetPacketType as u8], &[PacketType::Flrc as u8]),
+            vectors::Mt::busy(&spi, PinState::Low),
+            vectors::Mt::spi_write(&spi, &[Commands::SetModulationParams as u8], &modulation),
+            vectors::Mt::busy(&spi, PinState::Low),
+        ]);
+        v.append(&mut vectors::get_status(&spi, &sdn, &delay, State::StandbyRc, CommandStatus::Success));
+        v.append(&mut vectors::write_cmd(&spi, &sdn, &delay, Commands::SetPacketParams as u8, &[
+            modem.preamble_length as u8, modem.sync_word_length as u8, modem.sync_word_match as u8, modem.header_type as u8,
+            modem.payload_length, modem.crc_mode as u8, modem.whitening as u8,
+        ]));
+        v.append(&mut vectors::update_reg(&spi, &sdn, &delay, Registers::LrSyncWordTolerance as u16, 0x13, 0x10));
+        m.expect(v);
+
+        radio.set_modem(Modem::Flrc(modem.clone()), Channel::Flrc(channel)).unwrap();
+        m.finalise();
 
-        m.expect(vectors::firmware_version(&spi, &sdn, &delay, 16));
-        let version = radio.firmware_version().unwrap();
+        assert_eq!(radio.packet_type, PacketType::Flrc);
+        assert_eq!(radio.config.channel.frequency(), freq);
+        assert_eq!(radio.config.modem, Modem::Flrc(modem.clone()));
+
+        // Mismatched modem and channel pairs are rejected before any device writes
+        m.expect(vectors::none());
+        assert_eq!(radio.set_modem(Modem::Flrc(modem), Channel::LoRa(LoRaChannel::default())), Err(Error::InvalidConfiguration));
         m.finalise();
-        assert_eq!(version, 16);
     }
 
     #[test]
-    fn test_api_power_ramp() {
+    fn test_api_set_channel_batched() {
         let mut m = Mock::new();
         let (spi, sdn, _busy, delay) = (m.spi(), m.pin(), m.pin(), m.delay());
-        let mut radio = Sx128x::<Spi, _, _>::build(spi.clone());
+        let mut radio = Sx128x::<Spi, _, _, _>::build(spi.clone());
 
-        m.expect(vectors::set_power_ramp(&spi, &sdn, &delay, 0x1f, 0xe0));
-        radio.set_power_ramp(13, RampTime::Ramp20Us).unwrap();
+        let channel = LoRaChannel::default();
+        let steps = radio.config.freq_to_steps_u64(channel.freq);
+        let modulation = [channel.sf as u8, channel.bw as u8, channel.cr as u8];
+
+        // Packet type is included when changed
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::set_channel(&spi, &sdn, &delay, steps, Some(PacketType::LoRa as u8), &modulation));
+        m.expect(v);
+        radio.set_channel(&Channel::LoRa(channel.clone())).unwrap();
+        m.finalise();
+
+        // And omitted otherwise
+        let mut v = vectors::get_state(&spi, &sdn, &delay, State::StandbyRc);
+        v.append(&mut vectors::set_channel(&spi, &sdn, &delay, steps, None, &modulation));
+        m.expect(v);
+        radio.set_channel(&Channel::LoRa(channel)).unwrap();
         m.finalise();
     }
 }
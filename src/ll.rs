@@ -0,0 +1,10 @@
+//! Low-level device access
+//!
+//! This re-exports raw device commands and registers for use with the
+//! `read_command`, `write_command`, `read_register` and `write_register`
+//! methods on `Sx128x`, for functionality not (yet) wrapped by the driver.
+//!
+//! These are an unstable escape-hatch, bypassing driver state tracking,
+//! and may change between releases.
+
+pub use crate::device::{Commands, Registers};
@@ -0,0 +1,123 @@
+//! Radio-agnostic ping-pong link test, measuring round-trip time and packet loss
+//!
+//! Pings are sent and echoed by a remote device (eg. the utility `repeat` command), with
+//! delays and timing provided by the caller so this may be used (and tested) without `std`.
+
+
+use core::fmt::Debug;
+use core::time::Duration;
+
+use log::{debug, info, warn};
+
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Length of ping packets, encoded as a little-endian sequence number (u32)
+pub const PING_LEN: usize = 4;
+
+/// Round-trip time statistics reported by `pingpong`
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PingPongStats {
+    /// Number of pings sent
+    pub sent: u32,
+    /// Number of matching echoes received
+    pub received: u32,
+    /// Minimum round-trip time in microseconds
+    pub min_us: u32,
+    /// Average round-trip time in microseconds
+    pub avg_us: u32,
+    /// Maximum round-trip time in microseconds
+    pub max_us: u32,
+}
+
+impl PingPongStats {
+    /// Number of pings without a matching echo
+    pub fn lost(&self) -> u32 {
+        self.sent - self.received
+    }
+}
+
+/// Send `count` pings, waiting for each to be echoed
+///
+/// Round-trip times are measured with the `now_us` clock (a monotonic time in microseconds)
+/// from the start of each transmission to receipt of the matching echo. Echoes are polled
+/// every `poll_interval` using the provided `delay`, with the `timeout` applied as a number
+/// of poll intervals.
+pub fn pingpong<T, I, E, D, C>(radio: &mut T, delay: &mut D, mut now_us: C, count: u32, timeout: Duration, poll_interval: Duration) -> Result<PingPongStats, E>
+where
+    T: radio::Transmit<Error=E> + radio::Receive<Info=I, Error=E>,
+    I: Debug + Default,
+    D: DelayUs<u32>,
+    D::Error: Debug,
+    C: FnMut() -> u64,
+{
+    let poll_us = (poll_interval.as_micros() as u32).max(1);
+    let timeout_polls = timeout.as_micros() / poll_us as u128;
+
+    let mut stats = PingPongStats::default();
+    let mut total_us = 0u64;
+
+    let mut buff = [0u8; 255];
+    let mut info = I::default();
+
+    let wait = |delay: &mut D| {
+        if let Err(e) = delay.try_delay_us(poll_us) {
+            warn!("Ping-pong delay error: {:?}", e);
+        }
+    };
+
+    for seq in 0..count {
+        let ping = seq.to_le_bytes();
+
+        // Send ping
+        let sent = now_us();
+
+        radio.start_transmit(&ping)?;
+        while !radio.check_transmit()? {
+            wait(delay);
+        }
+        stats.sent += 1;
+
+        // Await matching echo
+        radio.start_receive()?;
+        let mut polls = 0;
+
+        loop {
+            if radio.check_receive(true)? {
+                let n = radio.get_received(&mut info, &mut buff)?;
+
+                if n >= PING_LEN && buff[..PING_LEN] == ping {
+                    let rtt = now_us().saturating_sub(sent).min(u32::MAX as u64) as u32;
+
+                    debug!("Ping {} RTT: {} us info: {:?}", seq, rtt, info);
+
+                    if stats.received == 0 || rtt < stats.min_us { stats.min_us = rtt; }
+                    if rtt > stats.max_us { stats.max_us = rtt; }
+                    stats.received += 1;
+                    total_us += rtt as u64;
+
+                    break;
+                }
+
+                debug!("Ignoring unexpected packet: {:x?}", &buff[..n]);
+                radio.start_receive()?;
+            }
+
+            if polls >= timeout_polls {
+                info!("Ping {} timed out", seq);
+                break;
+            }
+
+            wait(delay);
+            polls += 1;
+        }
+    }
+
+    if stats.received > 0 {
+        stats.avg_us = (total_us / stats.received as u64) as u32;
+    }
+
+    info!("Sent {} received {} lost {}, RTT min/avg/max: {}/{}/{} us",
+        stats.sent, stats.received, stats.lost(), stats.min_us, stats.avg_us, stats.max_us);
+
+    Ok(stats)
+}
@@ -1,6 +1,6 @@
 //! Common requirements for crate consumers
 
-pub use crate::{Sx128x, Sx128xSpi, Error as Sx128xError};
+pub use crate::{Sx128x, Sx128xSpi, Sx128xSpiPolarity, Error as Sx128xError};
 
 pub use crate::device::{Config, Modem, Channel, State, PacketInfo, RegulatorMode};
 
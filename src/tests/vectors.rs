@@ -10,12 +10,13 @@ use std::vec::Vec;
 
 use crate::device::*;
 
-pub fn reset(spi: &Spi, _sdn: &Pin, _delay: &Delay) -> Vec<Mt> {
+pub fn reset(spi: &Spi, _sdn: &Pin, _delay: &Delay, assert_ms: u32, settle_ms: u32) -> Vec<Mt> {
     vec![
+        Mt::delay_ms(20),
         Mt::reset(spi, PinState::Low),
-        Mt::delay_ms(1),
+        Mt::delay_ms(assert_ms),
         Mt::reset(spi, PinState::High),
-        Mt::delay_ms(10),
+        Mt::delay_ms(settle_ms),
     ]
 }
 
@@ -52,4 +53,222 @@ pub fn set_power_ramp(spi: &Spi, _sdn: &Pin, _delay: &Delay, power_reg: u8, ramp
         ]),
         Mt::busy(&spi, PinState::Low),
     ]
-}
\ No newline at end of file
+}
+pub fn sleep(spi: &Spi, _sdn: &Pin, _delay: &Delay, config: u8) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_write(&spi, &[Commands::SetSleep as u8], &[config]),
+    ]
+}
+
+pub fn wakeup(spi: &Spi, _sdn: &Pin, _delay: &Delay) -> Vec<Mt> {
+    vec![
+        Mt::spi_read(&spi, &[Commands::GetStatus as u8, 0], &[0x00]),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn none() -> Vec<Mt> {
+    vec![]
+}
+
+pub fn write_cmd(spi: &Spi, _sdn: &Pin, _delay: &Delay, cmd: u8, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_write(&spi, &[cmd], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn read_cmd(spi: &Spi, _sdn: &Pin, _delay: &Delay, cmd: u8, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_read(&spi, &[cmd, 0], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn get_state(spi: &Spi, sdn: &Pin, delay: &Delay, state: State) -> Vec<Mt> {
+    read_cmd(spi, sdn, delay, Commands::GetStatus as u8, &[(state as u8) << 5])
+}
+
+pub fn get_status(spi: &Spi, sdn: &Pin, delay: &Delay, state: State, status: CommandStatus) -> Vec<Mt> {
+    read_cmd(spi, sdn, delay, Commands::GetStatus as u8, &[(state as u8) << 5 | (status as u8) << 2])
+}
+
+pub fn get_irq(spi: &Spi, sdn: &Pin, delay: &Delay, irq: Irq) -> Vec<Mt> {
+    read_cmd(spi, sdn, delay, Commands::GetIrqStatus as u8, &[(irq.bits() >> 8) as u8, irq.bits() as u8])
+}
+
+pub fn set_channel(spi: &Spi, sdn: &Pin, delay: &Delay, freq_steps: u32, packet_type: Option<u8>, modulation: &[u8]) -> Vec<Mt> {
+    let mut v = vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_write(&spi, &[Commands::SetRfFrequency as u8], &[
+            (freq_steps >> 16) as u8, (freq_steps >> 8) as u8, freq_steps as u8,
+        ]),
+        Mt::busy(&spi, PinState::Low),
+    ];
+
+    if let Some(p) = packet_type {
+        v.push(Mt::spi_write(&spi, &[Commands::SetPacketType as u8], &[p]));
+        v.push(Mt::busy(&spi, PinState::Low));
+    }
+
+    v.push(Mt::spi_write(&spi, &[Commands::SetModulationParams as u8], modulation));
+    v.push(Mt::busy(&spi, PinState::Low));
+
+    v.append(&mut get_status(spi, sdn, delay, State::StandbyRc, CommandStatus::Success));
+
+    v
+}
+
+/// BUSY held high until `wait_busy` times out
+pub fn busy_timeout(spi: &Spi) -> Vec<Mt> {
+    let mut v = vec![];
+    for _ in 0..=BUSY_TIMEOUT_MS {
+        v.push(Mt::busy(&spi, PinState::High));
+        v.push(Mt::delay_ms(1));
+    }
+    v
+}
+
+pub fn write_buff(spi: &Spi, _sdn: &Pin, _delay: &Delay, offset: u8, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_write(&spi, &[Commands::WriteBuffer as u8, offset], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn read_buff(spi: &Spi, _sdn: &Pin, _delay: &Delay, offset: u8, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_read(&spi, &[Commands::ReadBuffer as u8, offset, 0], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn read_reg(spi: &Spi, sdn: &Pin, delay: &Delay, reg: u16, value: u8) -> Vec<Mt> {
+    read_regs(spi, sdn, delay, reg, &[value])
+}
+
+pub fn read_regs(spi: &Spi, _sdn: &Pin, _delay: &Delay, reg: u16, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_read(&spi, &[Commands::ReadRegister as u8, (reg >> 8) as u8, (reg >> 0) as u8, 0], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn write_reg(spi: &Spi, sdn: &Pin, delay: &Delay, reg: u16, value: u8) -> Vec<Mt> {
+    write_regs(spi, sdn, delay, reg, &[value])
+}
+
+pub fn write_regs(spi: &Spi, _sdn: &Pin, _delay: &Delay, reg: u16, data: &[u8]) -> Vec<Mt> {
+    vec![
+        Mt::busy(&spi, PinState::Low),
+        Mt::spi_write(&spi, &[Commands::WiteRegister as u8, (reg >> 8) as u8, (reg >> 0) as u8], data),
+        Mt::busy(&spi, PinState::Low),
+    ]
+}
+
+pub fn update_reg(spi: &Spi, sdn: &Pin, delay: &Delay, reg: u16, existing: u8, updated: u8) -> Vec<Mt> {
+    let mut v = read_reg(spi, sdn, delay, reg, existing);
+    v.append(&mut write_reg(spi, sdn, delay, reg, updated));
+    v
+}
+
+/// Sequence written by `start_transmit` for the default LoRa modem configuration,
+/// buffer base addresses and RF timeout
+pub fn start_transmit(spi: &Spi, sdn: &Pin, delay: &Delay, data: &[u8]) -> Vec<Mt> {
+    let c = crate::device::lora::LoRaConfig::default();
+    let irqs = (Irq::TX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT).bits();
+
+    let mut v = write_cmd(spi, sdn, delay, Commands::SetStandby as u8, &[0]);
+    v.append(&mut get_state(spi, sdn, delay, State::StandbyRc));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetPacketParams as u8, &[
+        c.preamble_length as u8, c.header_type as u8, data.len() as u8, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+    ]));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+    v.append(&mut write_buff(spi, sdn, delay, 0, data));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetDioIrqParams as u8, &[
+        (irqs >> 8) as u8, irqs as u8, (irqs >> 8) as u8, irqs as u8, 0, 0, 0, 0,
+    ]));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetTx as u8, &[0, 0, 0]));
+    v.append(&mut get_state(spi, sdn, delay, State::Tx));
+    v
+}
+
+/// `start_receive` sequence for the default (LoRa) configuration
+pub fn start_receive(spi: &Spi, sdn: &Pin, delay: &Delay) -> Vec<Mt> {
+    let c = crate::device::lora::LoRaConfig::default();
+    let irqs = (Irq::RX_DONE | Irq::CRC_ERROR | Irq::RX_TX_TIMEOUT | Irq::SYNCWORD_VALID
+        | Irq::SYNCWORD_ERROR | Irq::HEADER_VALID | Irq::HEADER_ERROR | Irq::PREAMBLE_DETECTED).bits();
+
+    let mut v = write_cmd(spi, sdn, delay, Commands::SetStandby as u8, &[0]);
+    v.append(&mut get_state(spi, sdn, delay, State::StandbyRc));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetBufferBaseAddress as u8, &[0, 0]));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetPacketParams as u8, &[
+        c.preamble_length as u8, c.header_type as u8, c.payload_length, c.crc_mode as u8, c.invert_iq as u8, 0, 0,
+    ]));
+    v.append(&mut update_reg(spi, sdn, delay, Registers::LnaRegime as u16, 0x00, 0x00));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetDioIrqParams as u8, &[
+        (irqs >> 8) as u8, irqs as u8, (irqs >> 8) as u8, irqs as u8, 0, 0, 0, 0,
+    ]));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetRx as u8, &[0, 0, 0]));
+    v.append(&mut get_state(spi, sdn, delay, State::Rx));
+    v
+}
+
+/// Configuration sequence written by `Sx128x::configure` for a LoRa `config`
+pub fn configure_sequence(spi: &Spi, sdn: &Pin, delay: &Delay, config: &Config) -> Vec<Mt> {
+    let modem = match &config.modem {
+        Modem::LoRa(c) => c,
+        _ => unimplemented!(),
+    };
+    let standby = match config.initial_standby {
+        State::StandbyXosc => 0x01,
+        _ => 0x00,
+    };
+    let power = config.pa_config.power.max(TX_POWER_MIN).min(TX_POWER_MAX);
+    let steps = config.freq_to_steps_u64(config.channel.frequency());
+
+    let mut v = write_cmd(spi, sdn, delay, Commands::SetStandby as u8, &[standby]);
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetRegulatorMode as u8, &[config.regulator_mode.resolve(power) as u8]));
+    v.append(&mut set_channel(spi, sdn, delay, steps, Some(PacketType::from(&config.channel) as u8), &config.channel.modulation_params()));
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetPacketParams as u8, &[
+        modem.preamble_length as u8, modem.header_type as u8, modem.payload_length as u8, modem.crc_mode as u8, modem.invert_iq as u8, 0, 0,
+    ]));
+    v.append(&mut set_power_ramp(spi, sdn, delay, (power - TX_POWER_MIN) as u8, config.pa_config.ramp_time as u8));
+    v.append(&mut update_reg(spi, sdn, delay, Registers::LnaRegime as u16, 0x00, if config.rx_boosted { MASK_LNA_REGIME } else { 0x00 }));
+    if let Some((tx, rx)) = config.buffer_base {
+        v.append(&mut write_cmd(spi, sdn, delay, Commands::SetBufferBaseAddress as u8, &[tx, rx]));
+    }
+    v
+}
+
+/// Initialisation sequence written by `Sx128x::new` following device detection
+pub fn init_sequence(spi: &Spi, sdn: &Pin, delay: &Delay, config: &Config) -> Vec<Mt> {
+    let mut v = none();
+    if !config.skip_calibration {
+        v.append(&mut write_cmd(spi, sdn, delay, Commands::Calibrate as u8, &[CalibrationParams::all().bits()]));
+    }
+
+    v.append(&mut configure_sequence(spi, sdn, delay, config));
+
+    let standby = match config.initial_standby {
+        State::StandbyXosc => 0x01,
+        _ => 0x00,
+    };
+    v.append(&mut write_cmd(spi, sdn, delay, Commands::SetStandby as u8, &[standby]));
+    v
+}
+
+/// Complete `Sx128x::new` sequence for a LoRa `config`, with the device reporting the
+/// first accepted firmware version
+pub fn new_sequence(spi: &Spi, sdn: &Pin, delay: &Delay, config: &Config) -> Vec<Mt> {
+    let mut v = reset(spi, sdn, delay, config.reset_assert_ms, config.reset_settle_ms);
+    v.append(&mut firmware_version(spi, sdn, delay, config.accepted_firmware_versions[0]));
+    v.append(&mut init_sequence(spi, sdn, delay, config));
+    v
+}
@@ -62,7 +62,7 @@ fn main() {
             return
         },
         _ => {
-            do_command(&mut radio, operation.unwrap()).expect("error executing command");
+            do_command(&mut radio, &rf_config, operation.unwrap()).expect("error executing command");
         }
     }
 
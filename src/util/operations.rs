@@ -1,6 +1,6 @@
 
 
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use std::fs::{File, OpenOptions};
 use std::ffi::CString;
 
@@ -12,12 +12,14 @@ use embedded_hal::blocking::delay::DelayUs;
 use driver_pal::hal::{HalDelay};
 use pcap_file::{PcapWriter, DataLink, pcap::PcapHeader};
 
+use radio_sx128x::prelude::*;
+use radio_sx128x::pingpong::pingpong;
+
 use super::options::*;
 
-pub fn do_command<T, I, E>(radio: &mut T, operation: Operation) -> Result<(), E> 
+pub fn do_command<T, E>(radio: &mut T, config: &Config, operation: Operation) -> Result<(), E> 
 where
-    T: radio::Transmit<Error=E> + radio::Power<Error=E> + radio::Receive<Info=I, Error=E>  + radio::Rssi<Error=E> + radio::Power<Error=E>,
-    I: Default + std::fmt::Debug,
+    T: radio::Transmit<Error=E> + radio::Power<Error=E> + radio::Receive<Info=PacketInfo, Error=E>  + radio::Rssi<Error=E> + radio::Power<Error=E>,
     E: std::fmt::Debug,
 {
     // TODO: the rest
@@ -28,14 +30,14 @@ where
         },
         Operation::Receive(config) => {
             let mut buff = [0u8; 255];
-            let mut info = I::default();
+            let mut info = PacketInfo::default();
 
             do_receive(radio, &mut buff, &mut info, &config)
                 .expect("Receive error");
         },
         Operation::Repeat(config) => {
             let mut buff = [0u8; 255];
-            let mut info = I::default();
+            let mut info = PacketInfo::default();
 
             do_repeat(radio, &mut buff, &mut info, config.power, config.continuous, *config.delay, *config.poll_interval)
                 .expect("Repeat error");
@@ -44,6 +46,21 @@ where
             do_rssi(radio, config.continuous, *config.period)
                 .expect("RSSI error");
         },
+        Operation::Sniff(options) => {
+            do_sniff(radio, config.channel.frequency(), &options)
+                .expect("Sniff error");
+        },
+        Operation::PingPong(options) => {
+            if let Some(p) = options.power {
+                radio.set_power(p).expect("Power error");
+            }
+
+            let start = Instant::now();
+            let now_us = || start.elapsed().as_micros() as u64;
+
+            pingpong(radio, &mut HalDelay{}, now_us, options.count, *options.timeout, *options.poll_interval)
+                .expect("Ping-pong error");
+        },
         //_ => warn!("unsuppored command: {:?}", opts.command),
     }
 
@@ -167,6 +184,69 @@ where
     }
 }
 
+/// Length of the synthetic link-layer header prepended to sniffed packets
+const SNIFF_HEADER_LEN: usize = 12;
+
+/// Build the synthetic link-layer header for a sniffed packet
+///
+/// This is little-endian encoded as: version (u8), reserved (u8), header length (u16),
+/// frequency in Hz (u32), RSSI in dBm (i16), SNR in dB (i16, `i16::MIN` if unavailable)
+fn sniff_header(freq: u32, info: &PacketInfo) -> [u8; SNIFF_HEADER_LEN] {
+    let mut h = [0u8; SNIFF_HEADER_LEN];
+
+    h[2..4].copy_from_slice(&(SNIFF_HEADER_LEN as u16).to_le_bytes());
+    h[4..8].copy_from_slice(&freq.to_le_bytes());
+    h[8..10].copy_from_slice(&info.rssi.to_le_bytes());
+    h[10..12].copy_from_slice(&info.snr.unwrap_or(i16::MIN).to_le_bytes());
+
+    h
+}
+
+fn do_sniff<T, E>(radio: &mut T, freq: u32, options: &Sniff) -> Result<usize, E> 
+where
+    T: radio::Receive<Info=PacketInfo, Error=E>,
+{
+    // Setup pcap writer with a user link type for the synthetic header
+    let f = File::create(&options.output).expect("Error creating PCAP file");
+
+    let mut h = PcapHeader::default();
+    h.datalink = DataLink::USER0;
+
+    let mut pcap = PcapWriter::with_header(h, f).expect("Error writing to PCAP file");
+
+    let mut buff = [0u8; SNIFF_HEADER_LEN + 255];
+    let mut info = PacketInfo::default();
+    let mut count = 0;
+
+    // Start receive mode
+    radio.start_receive()?;
+
+    loop {
+        if radio.check_receive(true)? {
+            let n = radio.get_received(&mut info, &mut buff[SNIFF_HEADER_LEN..])?;
+
+            info!("Captured {} bytes on {} Hz, info: {:?}", n, freq, info);
+
+            // Prepend header and write capture record
+            buff[..SNIFF_HEADER_LEN].copy_from_slice(&sniff_header(freq, &info));
+
+            let len = SNIFF_HEADER_LEN + n;
+            let t = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+
+            pcap.write(t.as_secs() as u32, t.subsec_micros(), &buff[..len], len as u32).expect("Error writing pcap file");
+
+            count += 1;
+            if Some(count) == options.count {
+                return Ok(count)
+            }
+
+            radio.start_receive()?;
+        }
+
+        HalDelay{}.try_delay_us(options.poll_interval.as_micros() as u32).unwrap();
+    }
+}
+
 fn do_rssi<T, I, E>(radio: &mut T, continuous: bool, period: Duration) -> Result<(), E> 
 where
     T: radio::Receive<Info=I, Error=E> + radio::Rssi<Error=E>,
@@ -205,7 +205,7 @@ pub struct FlrcCommand {
     /// FLRC bitrate-bandwidth in kbps
     /// (options: 2600_2400, 2080_2400, 1300_1200, 1040_1200, 650_600, 520_600, 325_300, 260_300)
     #[structopt(long = "br-bw", default_value="260_300", env="FLRC_BR_BW")]
-    pub bitrate_bandwidth: flrc::FlrcBitrate,
+    pub bitrate_bandwidth: flrc::FlrcBitrateBandwidth,
 
     /// FLRC coding rate
     /// (options: 3/4, 1/2, 1/0)
@@ -242,6 +242,14 @@ pub enum Operation {
     #[structopt(name="repeat")]
     /// Repeat received messages
     Repeat(Repeat),
+
+    #[structopt(name="sniff")]
+    /// Capture received packets with RSSI/SNR metadata to a PCAP file
+    Sniff(Sniff),
+
+    #[structopt(name="pingpong")]
+    /// Measure round-trip time and packet loss against a remote `repeat`
+    PingPong(PingPong),
 }
 
 #[derive(Clone, StructOpt, PartialEq, Debug)]
@@ -322,4 +330,38 @@ pub struct Repeat {
     /// Append RSSI and LQI to repeated message
     #[structopt(long = "append-info")]
     pub append_info: bool,
-}
\ No newline at end of file
+}
+
+#[derive(Clone, StructOpt, PartialEq, Debug)]
+pub struct Sniff {
+    /// PCAP file for capture output
+    #[structopt(long = "output")]
+    pub output: String,
+
+    /// Number of packets to capture (runs until interrupted if not specified)
+    #[structopt(long = "count")]
+    pub count: Option<usize>,
+
+    /// Specify period for polling for device status
+    #[structopt(long = "poll-interval", default_value="200ns")]
+    pub poll_interval: HumanDuration,
+}
+
+#[derive(Clone, StructOpt, PartialEq, Debug)]
+pub struct PingPong {
+    /// Number of pings to send
+    #[structopt(long = "count", default_value="10")]
+    pub count: u32,
+
+    /// Power in dBm (range -18dBm to 13dBm)
+    #[structopt(long = "power")]
+    pub power: Option<i8>,
+
+    /// Specify timeout waiting for each echo
+    #[structopt(long = "timeout", default_value="1s")]
+    pub timeout: HumanDuration,
+
+    /// Specify period for polling for device status (and RTT resolution)
+    #[structopt(long = "poll-interval", default_value="1ms")]
+    pub poll_interval: HumanDuration,
+}